@@ -39,12 +39,13 @@ impl EventHandler for GainWidget {
 }
 
 fn main() {
-    let mut app = Application::new(|win_desc, state, window| {
-
-        state.insert_theme(THEME);
-
+    // Themes passed to the builder can also set the cursor
+    let app = Application::new(|win_desc, state, window| {
         GainWidget::new().build(state, window, |builder| builder);
 
         win_desc.with_title("Hello GUI").with_inner_size(300,300)
-    });
+    })
+    .theme(THEME);
+
+    app.run().expect("Cannot open window");
 }
\ No newline at end of file
@@ -9,7 +9,7 @@ use tuix::events::BuildHandler;
 use tuix::style::themes::DEFAULT_THEME;
 
 fn main() {
-    let mut app = Application::new(|win_desc, state, window| {
+    let app = Application::new(|win_desc, state, window| {
 
         state.insert_theme(DEFAULT_THEME);
 
@@ -19,4 +19,6 @@ fn main() {
 
         win_desc.with_title("Hello GUI")
     });
+
+    app.run().expect("Cannot open window");
 }
\ No newline at end of file
@@ -5,531 +5,441 @@
 
 #![allow(deprecated)]
 
-use raw_window_handle::{HasRawWindowHandle};
+use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
 
 use tuix::window::{WindowDescription, WindowEvent, WindowWidget};
 
 use tuix::{Entity, State};
-use tuix::{Length, Visibility};
+use tuix::Length;
 
-use tuix::state::mouse::{MouseButton, MouseButtonState};
-
-use tuix::events::{Event, Propagation};
-
-use tuix::state::Fonts;
+use tuix::events::{Event, Message};
 
+use crate::clipboard;
+use crate::cursor;
 use crate::event_manager::EventManager;
+use crate::fonts::{FontData, FontError, FontEvent, FontSource};
+use crate::input::{InputHandler, InputSettings};
+use crate::render::DamageRenderer;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
 
 use femtovg::{
     renderer::OpenGl,
     Canvas,
-    Color,
 };
 
-use baseview::{WindowHandler, WindowScalePolicy};
-
-use raw_gl_context::GlContext;
+use baseview::gl::{GlConfig, GlContext};
+use baseview::{EventStatus, WindowHandler, WindowScalePolicy};
 
 struct OpenWindowExample {
-    context: GlContext,
     canvas: Canvas<OpenGl>,
     state: State,
     event_manager: EventManager,
+    input: InputHandler,
+    // Ratio of physical to logical pixels, updated from the baseview window info
+    scale_factor: f64,
+    handle_state: Arc<HandleState>,
+    callbacks: Receiver<StateCallback>,
+    renderer: DamageRenderer,
+}
+
+impl Drop for OpenWindowExample {
+    fn drop(&mut self) {
+        self.handle_state.open.store(false, Ordering::SeqCst);
+    }
+}
+
+// The GL context is created by baseview along with the window
+fn gl_context<'a>(window: &'a baseview::Window) -> &'a GlContext {
+    window.gl_context().expect("Window was opened without an OpenGL context")
 }
 
 impl WindowHandler for OpenWindowExample {
-    fn on_frame(&mut self) {
+    fn on_frame(&mut self, window: &mut baseview::Window) {
 
-        self.context.make_current();
+        if self.handle_state.close_requested.load(Ordering::SeqCst) {
+            window.close();
+            return;
+        }
 
-        let width = self.state.transform.get_width(self.state.root);
-        let height = self.state.transform.get_height(self.state.root);
+        // Run anything posted to the state through an application handle
+        let mut posted = false;
+        while let Ok(callback) = self.callbacks.try_recv() {
+            callback(&mut self.state);
+            posted = true;
+        }
 
-        self.canvas.set_size(width as u32, height as u32, 1.0);
-        self.canvas.clear_rect(0, 0, width as u32, height as u32, Color::rgb(80, 80, 80));
+        if posted {
+            self.state.insert_event(Event::new(WindowEvent::Redraw).target(Entity::null()).origin(self.state.root));
+        }
 
-        let hierarchy = self.state.hierarchy.clone();
+        // Input is handled as it arrives, so the damage from it is drawn here along with anything new
+        self.event_manager.update(&mut self.state);
 
-        if self.state.apply_animations() {
-            self.state.insert_event(Event::new(WindowEvent::Relayout).target(Entity::null()).origin(Entity::new(0, 0)));
-            //self.state.insert_event(Event::new(WindowEvent::Redraw));
+        if let Some(cursor_icon) = self.event_manager.cursor.take() {
+            window.set_mouse_cursor(cursor::to_mouse_cursor(cursor_icon));
         }
 
-        self.state.insert_event(Event::new(WindowEvent::Restyle).target(Entity::null()).origin(Entity::new(0, 0)));
-        self.state.insert_event(Event::new(WindowEvent::Relayout).target(Entity::null()).origin(Entity::new(0, 0)));
+        let damage = std::mem::take(&mut self.event_manager.damage);
 
-        //while !self.state.event_queue.is_empty() {
-            self.event_manager.flush_events(&mut self.state);
+        // Nothing changed since the last frame so there's nothing to draw
+        if damage.is_empty() {
+            return;
+        }
 
+        let context = gl_context(window);
 
+        unsafe { context.make_current() };
 
-            self.event_manager.draw(&mut self.state, &hierarchy, &mut self.canvas);
-            
-        //}
+        self.renderer.render(&mut self.canvas, &mut self.state, &mut self.event_manager, damage, self.scale_factor as f32);
 
-        
+        self.canvas.flush();
+        context.swap_buffers();
+    }
 
-        // unsafe {
-        //     gl::ClearColor(1.0, 0.0, 1.0, 1.0);
-        //     gl::Clear(gl::COLOR_BUFFER_BIT);
-        // }
+    fn on_event(&mut self, window: &mut baseview::Window, event: baseview::Event) -> EventStatus {
+        match event {
+            baseview::Event::Window(e) => {
+                match e {
+                    baseview::WindowEvent::Resized(window_info) => {
+                        let width = window_info.logical_size().width as f32;
+                        let height = window_info.logical_size().height as f32;
 
+                        let root = self.state.root;
 
+                        self.state.style.width.insert(root, Length::Pixels(width));
+                        self.state.style.height.insert(root, Length::Pixels(height));
 
+                        self.state.transform.set_width(root, width);
+                        self.state.transform.set_height(root, height);
 
+                        self.scale_factor = window_info.scale();
 
-        //draw_colorwheel(&mut self.canvas, 200.0, 200.0, 200.0, 200.0, 0.0);
+                        let physical_size = window_info.physical_size();
 
-        self.canvas.flush();
-        self.context.swap_buffers();
-    }
+                        unsafe { gl_context(window).make_current() };
+                        self.canvas.set_size(physical_size.width, physical_size.height, self.scale_factor as f32);
 
-    fn on_event(&mut self, _window: &mut baseview::Window, event: baseview::Event) {
-        match event {
-            baseview::Event::Mouse(e) => {
-                match e {
-                    baseview::MouseEvent::CursorMoved{position} => {
-
-                        //println!("Cursor Moved");
-
-                        self.state.insert_event(Event::new(WindowEvent::Restyle).target(Entity::null()).origin(Entity::new(0, 0)));
-                        self.state.insert_event(Event::new(WindowEvent::Relayout).target(Entity::null()).origin(Entity::new(0, 0)));
-
-                        let cursorx = (position.x) as f32;
-                        let cursory = (position.y) as f32;
-
-                        self.state.mouse.cursorx = cursorx as f32;
-                        self.state.mouse.cursory = cursory as f32;
-
-                        let mut hovered_widget = Entity::new(0, 0);
-
-                        // This only really needs to be computed when the hierarchy changes
-                        // Can be optimised
-                        let mut draw_hierarchy: Vec<Entity> = self.state.hierarchy.into_iter().collect();
-
-                        draw_hierarchy.sort_by_cached_key(|entity| self.state.transform.get_z_order(*entity));
-
-
-                        for widget in draw_hierarchy.into_iter() {
-                            // Skip invisible widgets
-                            if self.state.transform.get_visibility(widget) == Visibility::Invisible
-                            {
-                                continue;
-                            }
-                            
-                            // This shouldn't be here but there's a bug if it isn't 
-                            if self.state.transform.get_opacity(widget) == 0.0 {
-                                continue;
-                            }
-                            
-                            // Skip non-hoverable widgets
-                            if self.state.transform.get_hoverability(widget) != true {
-                                continue;
-                            }
-
-                            let border_width = match self.state
-                                .style
-                                .border_width
-                                .get(widget)
-                                .cloned()
-                                .unwrap_or_default() 
-                            {
-                                Length::Pixels(val) => val,
-                                //Length::Percentage(val) => parent_width * val,
-                                _ => 0.0,
-                            };
-
-                            let posx = self.state.transform.get_posx(widget) - (border_width / 2.0);
-                            let posy = self.state.transform.get_posy(widget) - (border_width / 2.0);
-                            let width = self.state.transform.get_width(widget) + (border_width);
-                            let height = self.state.transform.get_height(widget) + (border_width);
-
-                            let clip_widget = self.state.transform.get_clip_widget(widget);
-
-
-                            let clip_posx = self.state.transform.get_posx(clip_widget);
-                            let clip_posy = self.state.transform.get_posy(clip_widget);
-                            let clip_width = self.state.transform.get_width(clip_widget);
-                            let clip_height = self.state.transform.get_height(clip_widget);
-
-                            if cursorx >= posx && cursorx >= clip_posx
-                                && cursorx < (posx + width) && cursorx < (clip_posx + clip_width)
-                                && cursory >= posy && cursory >= clip_posy
-                                && cursory < (posy + height) && cursory < (clip_posy + clip_height)
-                            {
-                                hovered_widget = widget;
-                                if let Some(pseudo_classes) = self.state.style.pseudo_classes.get_mut(hovered_widget) {
-                                    pseudo_classes.set_over(true);
-                                }
-                            } else {
-                                if let Some(pseudo_classes) = self.state.style.pseudo_classes.get_mut(hovered_widget) {
-                                    pseudo_classes.set_over(false);
-                                }
-                            }
-                        }
-
-                        if hovered_widget != self.state.hovered {
-
-                            // Useful for debugging
-                        
-                            println!(
-                                "Hover changed to {:?} parent: {:?}, posx: {}, posy: {} width: {} height: {} z_order: {}",
-                                hovered_widget,
-                                self.state.hierarchy.get_parent(hovered_widget),
-                                self.state.transform.get_posx(hovered_widget),
-                                self.state.transform.get_posy(hovered_widget),
-                                self.state.transform.get_width(hovered_widget),
-                                self.state.transform.get_height(hovered_widget),
-                                self.state.transform.get_z_order(hovered_widget),
-                            );
-
-                            if let Some(pseudo_classes) = self.state.style.pseudo_classes.get_mut(hovered_widget) {
-                                pseudo_classes.set_hover(true);
-                            }
-
-                            if let Some(pseudo_classes) = self.state.style.pseudo_classes.get_mut(self.state.hovered) {
-                                pseudo_classes.set_hover(false);
-                            }
-
-                            self.state.insert_event(Event::new(WindowEvent::MouseOver).target(hovered_widget));
-                            self.state.insert_event(Event::new(WindowEvent::MouseOut).target(self.state.hovered));
-
-                            self.state.hovered = hovered_widget;
-                            self.state.active = Entity::null();
-
-                            self.state
-                                .insert_event(Event::new(WindowEvent::Restyle));
-                            self.state
-                                .insert_event(Event::new(WindowEvent::Redraw));
-                        }
-
-                        if self.state.captured != Entity::null() {
-                            self.state.insert_event(
-                                Event::new(WindowEvent::MouseMove(cursorx, cursory))
-                                    .target(self.state.captured)
-                                    .propagate(Propagation::Direct),
-                            );
-                        } else if self.state.hovered != Entity::new(0, 0) {
-                            self.state.insert_event(
-                                Event::new(WindowEvent::MouseMove(cursorx, cursory))
-                                    .target(self.state.hovered),
-                            );
-                        }
-                    }
+                        self.state.insert_event(Event::new(WindowEvent::WindowResize(width, height)).target(root));
+                        self.state.insert_event(Event::new(WindowEvent::Restyle).target(Entity::null()).origin(root));
+                        self.state.insert_event(Event::new(WindowEvent::Relayout).target(Entity::null()).origin(root));
+                        self.state.insert_event(Event::new(WindowEvent::Redraw));
 
-                    baseview::MouseEvent::ButtonPressed(button) => {
-                        let b = match button {
-                            baseview::MouseButton::Left => MouseButton::Left,
-                            baseview::MouseButton::Right => MouseButton::Right,
-                            baseview::MouseButton::Middle => MouseButton::Middle,
-                            baseview::MouseButton::Other(id) => MouseButton::Other(id as u16),
-                            _=> MouseButton::Left,
-                        };
-
-                        match b {
-                            MouseButton::Left => {
-                                self.state.mouse.left.state = MouseButtonState::Pressed;
-                            }
-
-                            MouseButton::Right => {
-                                self.state.mouse.right.state = MouseButtonState::Pressed;
-                            }
-
-                            MouseButton::Middle => {
-                                self.state.mouse.middle.state = MouseButtonState::Pressed;
-                            }
-
-                            _ => {}
-                        }
-
-                        if self.state.hovered != Entity::null()
-                            && self.state.active != self.state.hovered
-                        {
-                            self.state.active = self.state.hovered;
-                            self.state.insert_event(Event::new(WindowEvent::Restyle));
-                        }
-
-                        if self.state.captured != Entity::null() {
-                            self.state.insert_event(
-                                Event::new(WindowEvent::MouseDown(b))
-                                    .target(self.state.captured)
-                                    .propagate(Propagation::Direct),
-                            );
-                        } else {
-                            self.state.insert_event(
-                                Event::new(WindowEvent::MouseDown(b))
-                                    .target(self.state.hovered),
-                            );
-                        }
-
-                        match b {
-                            MouseButton::Left => {
-                                self.state.mouse.left.pos_down = (self.state.mouse.cursorx, self.state.mouse.cursory);
-                                self.state.mouse.left.pressed = self.state.hovered;
-                            }
-
-                            MouseButton::Middle => {
-                                self.state.mouse.middle.pos_down = (self.state.mouse.cursorx, self.state.mouse.cursory);
-                                self.state.mouse.left.pressed = self.state.hovered;
-                            }
-                            
-                            MouseButton::Right => {
-                                self.state.mouse.right.pos_down = (self.state.mouse.cursorx, self.state.mouse.cursory);
-                                self.state.mouse.left.pressed = self.state.hovered;
-                            } 
-                            
-                            _ => {}
-                        }
-                            
+                        EventStatus::Captured
                     }
 
-                    baseview::MouseEvent::ButtonReleased(button) => {
-
-                        let b = match button {
-                            baseview::MouseButton::Left => MouseButton::Left,
-                            baseview::MouseButton::Right => MouseButton::Right,
-                            baseview::MouseButton::Middle => MouseButton::Middle,
-                            baseview::MouseButton::Other(id) => MouseButton::Other(id as u16),
-                            _=> MouseButton::Left,
-                        };
-
-                        match b {
-                            MouseButton::Left => {
-                                self.state.mouse.left.state = MouseButtonState::Released;
-                            }
-
-                            MouseButton::Right => {
-                                self.state.mouse.right.state = MouseButtonState::Released;
-                            }
-
-                            MouseButton::Middle => {
-                                self.state.mouse.middle.state = MouseButtonState::Released;
-                            }
-
-                            _ => {}
-                        }
-
-                        self.state.active = Entity::null();
-                        self.state.insert_event(Event::new(WindowEvent::Restyle));
-
-                        if self.state.captured != Entity::null() {
-                            self.state.insert_event(
-                                Event::new(WindowEvent::MouseUp(b))
-                                    .target(self.state.captured)
-                                    .propagate(Propagation::Direct),
-                            );
-                        } else {
-                            self.state.insert_event(
-                                Event::new(WindowEvent::MouseUp(b))
-                                    .target(self.state.hovered),
-                            );
-                        }
-
-                        match b {
-                            MouseButton::Left => {
-                                self.state.mouse.left.pos_up = (self.state.mouse.cursorx, self.state.mouse.cursory);
-                                self.state.mouse.left.released = self.state.hovered;
-                            }
-                            
-                            MouseButton::Middle => {
-                                self.state.mouse.middle.pos_up = (self.state.mouse.cursorx, self.state.mouse.cursory);
-                                self.state.mouse.left.released = self.state.hovered;
-                            } 
-                                
-                            MouseButton::Right => {
-                                self.state.mouse.right.pos_up = (self.state.mouse.cursorx, self.state.mouse.cursory);
-                                self.state.mouse.left.released = self.state.hovered;
-                            }
-
-                            _ => {}
-                        }
+                    baseview::WindowEvent::WillClose => {
+                        // The canvas is dropped before the window and its context, and needs the
+                        // context to be current to free its GL resources
+                        unsafe { gl_context(window).make_current() };
+
+                        EventStatus::Ignored
                     }
 
-                    _=> {}
+                    _=> EventStatus::Ignored,
                 }
-                //println!("Mouse event: {:?}", e)
-            },
+            }
 
-            _=> {}
+            input => self.input.handle_input(&mut self.event_manager, &mut self.state, input),
+        }
+    }
+}
 
-            /*
-            baseview::Event::Keyboard(e) => {
-                match e {
-                    KeyboardEvent {state: s, key, code, location, modifiers, repeat, is_composing} => {
-                        
-                        
-                        if s == KeyState::Down {
-                            self.state.insert_event(
-                                Event::new(WindowEvent::KeyInput(
-                                    KeyboardInput {
-                                        scancode: 0,
-                                        virtual_keycode: Some(crate::VirtualKeyCode::Z),
-                                        state: MouseButtonState::Pressed,
-                                    }
-                                ))
-                                .target(self.state.hovered)
-                            );
-                        }
-
-                        if s == KeyState::Up {
-                            self.state.insert_event(
-                                Event::new(WindowEvent::KeyInput(
-                                    KeyboardInput {
-                                        scancode: 0,
-                                        virtual_keycode: Some(crate::VirtualKeyCode::Z),
-                                        state: MouseButtonState::Released,
-                                    }
-                                ))
-                                .target(self.state.hovered)
-                            );
-                        }
-
-
-                        
-                        
-                    }
-                }
-                //println!("Keyboard event: {:?}", e);
-            },
-            */
-            
-            
-            
-            baseview::Event::Window(e) => println!("Window event: {:?}", e),
+type StateCallback = Box<dyn FnOnce(&mut State) + Send>;
+
+struct HandleState {
+    open: AtomicBool,
+    close_requested: AtomicBool,
+}
+
+/// A handle to a tuix window which can be used to close the window, check whether it
+/// is still open, and post events into its state from outside of the window.
+#[derive(Clone)]
+pub struct ApplicationHandle {
+    handle_state: Arc<HandleState>,
+    sender: Sender<StateCallback>,
+}
+
+impl ApplicationHandle {
+    fn new() -> (Self, Receiver<StateCallback>) {
+        let (sender, receiver) = channel();
+
+        let handle = ApplicationHandle {
+            handle_state: Arc::new(HandleState {
+                open: AtomicBool::new(false),
+                close_requested: AtomicBool::new(false),
+            }),
+            sender,
+        };
+
+        (handle, receiver)
+    }
+
+    /// Close the window
+    pub fn close(&mut self) {
+        self.handle_state.close_requested.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns `true` if the window is open, and `false` if it hasn't been opened yet or
+    /// has been closed.
+    pub fn is_open(&self) -> bool {
+        self.handle_state.open.load(Ordering::SeqCst)
+    }
+
+    /// Run a closure with the window state before the next frame is drawn
+    pub fn post<F: FnOnce(&mut State) + Send + 'static>(&self, callback: F) {
+        // The receiver is gone if the window has been closed, in which case there's nothing to do
+        let _ = self.sender.send(Box::new(callback));
+    }
+
+    /// Send an event to an entity in the window before the next frame is drawn
+    pub fn send_event<M: Message + Send>(&self, message: M, target: Entity) {
+        self.post(move |state| state.insert_event(Event::new(message).target(target)));
+    }
+}
+
+/// Keeps a window opened with `ApplicationBuilder::open` alive, whether it has a parent or not.
+/// The window is closed when `close` is called or the handle is dropped, so the handle needs to
+/// be stored for as long as the window should stay open.
+#[must_use = "the window is closed as soon as its handle is dropped"]
+pub struct WindowHandle {
+    // Windows with a parent are closed through baseview's handle
+    window_handle: Option<baseview::WindowHandle>,
+    // Windows without a parent run on a thread of their own, which ends once the window is closed
+    thread: Option<JoinHandle<()>>,
+    handle: ApplicationHandle,
+}
+
+impl WindowHandle {
+    /// Close the window, dropping its GL context and state. Waits for the window to close.
+    pub fn close(&mut self) {
+        self.handle.close();
+
+        if let Some(window_handle) = &mut self.window_handle {
+            window_handle.close();
+        }
+
+        if let Some(thread) = self.thread.take() {
+            // The window's own thread can't wait for itself, e.g. when the handle is dropped with the state
+            if thread.thread().id() != std::thread::current().id() {
+                // A panic in the window has already been reported on its thread
+                let _ = thread.join();
+            }
         }
     }
+
+    /// Returns `true` if the window is still open
+    pub fn is_open(&self) -> bool {
+        self.window_handle.as_ref().map_or(true, |window_handle| window_handle.is_open()) && self.handle.is_open()
+    }
+
+    /// Returns a handle which can be used to post events to the window from another thread
+    pub fn handle(&self) -> ApplicationHandle {
+        self.handle.clone()
+    }
 }
 
-pub struct Application {
-    //pub state: State,
-    //pub event_manager: EventManager,
-    //pub app_runner: Option<baseview::AppRunner>
+impl Drop for WindowHandle {
+    fn drop(&mut self) {
+        self.close();
+    }
 }
 
-impl Application {
-    pub fn new<F: FnMut(WindowDescription, &mut State, Entity) -> WindowDescription>(
-        mut app: F,
-    ) -> Self 
-    {
-        
-        let mut state = State::new();
-        let root = state.root;
+// Stores the raw handle of a parent window so the builder doesn't need to borrow it
+struct ParentWindow(RawWindowHandle);
 
-        
+unsafe impl HasRawWindowHandle for ParentWindow {
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        self.0
+    }
+}
 
-        WindowWidget::new().build_window(&mut state);
+pub type Application<F> = ApplicationBuilder<F>;
+
+/// Describes a tuix window and opens it in one of the modes supported by baseview.
+///
+/// The app closure is called each time a window is opened to build a fresh state, so the same
+/// builder can open a window again after it has been closed.
+///
+/// Creating the builder doesn't open anything, so `Application::new(..)` on its own does nothing.
+/// Call `run` or `open` to show the window.
+#[must_use = "the window is only opened by `run` or `open`"]
+pub struct ApplicationBuilder<F> {
+    app: F,
+    title: Option<String>,
+    inner_size: Option<(u32, u32)>,
+    parent: Option<ParentWindow>,
+    scale_policy: WindowScalePolicy,
+    pub(crate) fonts: FontData,
+    themes: Vec<String>,
+    input_settings: InputSettings,
+    show_damage: bool,
+    handle: ApplicationHandle,
+    callbacks: Receiver<StateCallback>,
+}
 
-        state.hierarchy.add(state.root, None);
+impl<F: FnMut(WindowDescription, &mut State, Entity) -> WindowDescription> ApplicationBuilder<F> {
+    pub fn new(app: F) -> Self {
+        let (handle, callbacks) = ApplicationHandle::new();
+
+        ApplicationBuilder {
+            app,
+            title: None,
+            inner_size: None,
+            parent: None,
+            scale_policy: WindowScalePolicy::SystemScaleFactor,
+            fonts: FontData::default(),
+            themes: Vec::new(),
+            input_settings: InputSettings::default(),
+            show_damage: false,
+            handle,
+            callbacks,
+        }
+    }
 
-        let root = state.root;
+    /// Open the window as a child of `parent`. It stays open until the returned handle is dropped.
+    pub fn new_with_parent<P: HasRawWindowHandle>(
+        parent: &P, app: F,
+    ) -> Result<WindowHandle, FontError> {
+        Self::new(app).parent(parent).open()
+    }
 
-        state.insert_event(Event::new(WindowEvent::Restyle));
-        state.insert_event(Event::new(WindowEvent::Relayout).target(Entity::null()));
+    /// Open the window on a thread of its own. It stays open until the returned handle is dropped,
+    /// so `let _ = Application::new_detached(..)` closes it again straight away.
+    pub fn new_detached(app: F) -> Result<WindowHandle, FontError> {
+        Self::new(app).open()
+    }
 
-        let regular_font = include_bytes!("../resources/Roboto-Regular.ttf");
-        let bold_font = include_bytes!("../resources/Roboto-Bold.ttf");
-        let icon_font = include_bytes!("../resources/entypo.ttf");
+    /// Set the window title. The app closure can still override it.
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = Some(title.to_owned());
+        self
+    }
 
+    /// Set the logical size of the window. The app closure can still override it.
+    pub fn inner_size(mut self, width: u32, height: u32) -> Self {
+        self.inner_size = Some((width, height));
+        self
+    }
 
+    /// Open the window as a child of `parent`, for example a plugin editor inside a host
+    pub fn parent<P: HasRawWindowHandle>(mut self, parent: &P) -> Self {
+        self.parent = Some(ParentWindow(parent.raw_window_handle()));
+        self
+    }
 
-        //let event_manager = EventManager::new();
+    pub fn scale_policy(mut self, scale_policy: WindowScalePolicy) -> Self {
+        self.scale_policy = scale_policy;
+        self
+    }
 
-        //let window_description = win(WindowDescription::new());
+    /// Replace all of the fonts registered when the window is opened
+    pub fn fonts(mut self, fonts: FontData) -> Self {
+        self.fonts = fonts;
+        self
+    }
 
-        let window_description = app(WindowDescription::new(), &mut state, root);
+    pub fn regular_font<S: Into<FontSource>>(mut self, source: S) -> Self {
+        self.fonts.regular = Some(source.into());
+        self
+    }
 
-        let width = window_description.inner_size.to_physical(1.0).width;
-        let height = window_description.inner_size.to_physical(1.0).height; 
+    pub fn bold_font<S: Into<FontSource>>(mut self, source: S) -> Self {
+        self.fonts.bold = Some(source.into());
+        self
+    }
 
-        state.style.width.insert(
-            state.root,
-            Length::Pixels(width as f32),
-        );
+    pub fn icon_font<S: Into<FontSource>>(mut self, source: S) -> Self {
+        self.fonts.icons = Some(source.into());
+        self
+    }
 
-        state.style.height.insert(
-            state.root,
-            Length::Pixels(height as f32),
-        );
+    /// Register an additional font under `name`
+    pub fn font<S: Into<FontSource>>(mut self, name: &str, source: S) -> Self {
+        self.fonts.named.push((name.to_owned(), source.into()));
+        self
+    }
 
-        state.transform.set_width(
-            state.get_root(),
-            width as f32,
-        );
-        state.transform.set_height(
-            state.get_root(),
-            height as f32,
-        );
-        state.transform.set_opacity(state.get_root(), 1.0);
+    /// Add a stylesheet, applied before the app closure is called. Can be called more than once.
+    pub fn theme(mut self, theme: &str) -> Self {
+        self.themes.push(theme.to_owned());
+        self
+    }
 
-        let window_open_options = baseview::WindowOpenOptions {
-            title: window_description.title,
-            size: baseview::Size::new(width, height),
-            scale: WindowScalePolicy::SystemScaleFactor,
-            //parent: baseview::Parent::None,
+    pub fn input_settings(mut self, input_settings: InputSettings) -> Self {
+        self.input_settings = input_settings;
+        self
+    }
+
+    /// Outline the areas of the window which are drawn again each frame, for debugging
+    pub fn show_damage(mut self, show_damage: bool) -> Self {
+        self.show_damage = show_damage;
+        self
+    }
+
+    /// Returns a handle to the next window opened by this application, which can be used
+    /// from another thread while `run` blocks
+    pub fn handle(&self) -> ApplicationHandle {
+        self.handle.clone()
+    }
+
+    /// Open the window and block the current thread until it is closed. Any parent is ignored.
+    pub fn run(mut self) -> Result<(), FontError> {
+        let (window_open_options, _, build) = self.prepare()?;
+
+        baseview::Window::open_blocking(window_open_options, build);
+
+        Ok(())
+    }
+
+    /// Open the window without blocking the current thread, as a child of the parent window if
+    /// one was set. The builder can be used to open the window again once it has been closed.
+    ///
+    /// A window without a parent runs on a thread of its own, which macOS doesn't allow. Use
+    /// `run` there instead.
+    ///
+    /// The window is closed when the returned handle is dropped.
+    pub fn open(&mut self) -> Result<WindowHandle, FontError> {
+        let (window_open_options, handle, build) = self.prepare()?;
+
+        let (window_handle, thread) = match &self.parent {
+            Some(parent) => (Some(baseview::Window::open_parented(parent, window_open_options, build)), None),
+            None => {
+                // Baseview only opens windows without a parent by blocking, so the window gets a
+                // thread of its own
+                let thread = std::thread::spawn(move || baseview::Window::open_blocking(window_open_options, build));
+                (None, Some(thread))
+            }
         };
 
-        baseview::Window::open_blocking(
-            window_open_options,
-            move |window| {
-                let context = GlContext::create(window, Default::default()).unwrap();
-                context.make_current();
-                gl::load_with(|symbol| context.get_proc_address(symbol) as *const _);
-                let renderer = OpenGl::new(|symbol| context.get_proc_address(symbol) as *const _).expect("Cannot create renderer");
-                let mut canvas = Canvas::new(renderer).expect("Cannot create canvas");
-    
-                // let fonts = Fonts {
-                //     regular: Some(canvas
-                //         .add_font("examples/resources/Roboto-Regular.ttf")
-                //         .expect("Cannot add font")),
-                //     bold: Some(canvas
-                //         .add_font("examples/resources/Roboto-Bold.ttf")
-                //         .expect("Cannot add font")),
-                //     icons: Some(canvas.add_font("examples/resources/entypo.ttf").expect("Cannot add font")),
-                // };
-
-                // state.fonts = fonts;
-
-                //let mut state = state.clone();
-
-                //win(&mut state, root);
-
-                let fonts = Fonts {
-                    regular: Some(canvas
-                        .add_font_mem(regular_font)
-                        .expect("Cannot add font")),
-                    bold: Some(canvas
-                        .add_font_mem(bold_font)
-                        .expect("Cannot add font")),
-                    icons: Some(canvas.add_font_mem(icon_font).expect("Cannot add font")),
-                };
-        
-                state.fonts = fonts;
-
-                OpenWindowExample {context, canvas, state, event_manager: EventManager::new()}
-            } 
-        );
+        Ok(WindowHandle {
+            window_handle,
+            thread,
+            handle,
+        })
+    }
 
- 
-        Application {
-            // event_manager: event_manager,
-            // state: state,
-            //app_runner: opt_app_runner,
-        }
+    // Hands out the handle for the window about to be opened and prepares a new one for the next
+    fn take_handle(&mut self) -> (ApplicationHandle, Receiver<StateCallback>) {
+        let (handle, callbacks) = ApplicationHandle::new();
+
+        (
+            std::mem::replace(&mut self.handle, handle),
+            std::mem::replace(&mut self.callbacks, callbacks),
+        )
     }
 
+    // Translates input with the input settings. The cursor rules are read from the themes in the state.
+    pub(crate) fn input_handler(&self) -> InputHandler {
+        InputHandler::new(self.input_settings)
+    }
 
-    pub fn new_with_parent<P, F: FnMut(WindowDescription, &mut State, Entity) -> WindowDescription>(
-        parent: &P, mut app: F,
-    ) -> Self 
-    
-    where P: HasRawWindowHandle
-    
-    {
-        
+    // Builds a fresh state from the app closure, so that a closed window can be opened again
+    pub(crate) fn build_state(&mut self) -> (State, baseview::WindowOpenOptions) {
         let mut state = State::new();
-        let root = state.root;
-
-        
 
         WindowWidget::new().build_window(&mut state);
 
@@ -540,18 +450,24 @@ impl Application {
         state.insert_event(Event::new(WindowEvent::Restyle));
         state.insert_event(Event::new(WindowEvent::Relayout).target(Entity::null()));
 
-        let regular_font = include_bytes!("../resources/Roboto-Regular.ttf");
-        let bold_font = include_bytes!("../resources/Roboto-Bold.ttf");
-        let icon_font = include_bytes!("../resources/entypo.ttf");
-
+        for theme in self.themes.iter() {
+            state.insert_theme(theme);
+        }
 
+        let mut window_description = WindowDescription::new();
 
-        //let event_manager = EventManager::new();
+        if let Some(title) = &self.title {
+            window_description = window_description.with_title(title);
+        }
 
-        //let window_description = win(WindowDescription::new());
+        if let Some((width, height)) = self.inner_size {
+            window_description = window_description.with_inner_size(width, height);
+        }
 
-        let window_description = app(WindowDescription::new(), &mut state, root);
+        let window_description = (self.app)(window_description, &mut state, root);
 
+        // The requested size is in logical pixels. The physical size isn't known until
+        // baseview reports the system scale factor with the first resize event.
         let width = window_description.inner_size.to_physical(1.0).width;
         let height = window_description.inner_size.to_physical(1.0).height; 
 
@@ -578,58 +494,79 @@ impl Application {
         let window_open_options = baseview::WindowOpenOptions {
             title: window_description.title,
             size: baseview::Size::new(width, height),
-            scale: WindowScalePolicy::SystemScaleFactor,
-            //parent: baseview::Parent::None,
+            scale: self.scale_policy,
+            gl_config: Some(GlConfig::default()),
         };
 
-        baseview::Window::open_parented(
-            parent,
-            window_open_options,
-            move |window| {
-                let context = GlContext::create(window, Default::default()).unwrap();
-                context.make_current();
-                gl::load_with(|symbol| context.get_proc_address(symbol) as *const _);
-                let renderer = OpenGl::new(|symbol| context.get_proc_address(symbol) as *const _).expect("Cannot create renderer");
-                let mut canvas = Canvas::new(renderer).expect("Cannot create canvas");
-    
-                // let fonts = Fonts {
-                //     regular: Some(canvas
-                //         .add_font("examples/resources/Roboto-Regular.ttf")
-                //         .expect("Cannot add font")),
-                //     bold: Some(canvas
-                //         .add_font("examples/resources/Roboto-Bold.ttf")
-                //         .expect("Cannot add font")),
-                //     icons: Some(canvas.add_font("examples/resources/entypo.ttf").expect("Cannot add font")),
-                // };
-
-                // state.fonts = fonts;
-
-                //let mut state = state.clone();
-
-                //win(&mut state, root);
-
-                let fonts = Fonts {
-                    regular: Some(canvas
-                        .add_font_mem(regular_font)
-                        .expect("Cannot add font")),
-                    bold: Some(canvas
-                        .add_font_mem(bold_font)
-                        .expect("Cannot add font")),
-                    icons: Some(canvas.add_font_mem(icon_font).expect("Cannot add font")),
-                };
-        
-                state.fonts = fonts;
-
-                OpenWindowExample {context, canvas, state, event_manager: EventManager::new()}
-            } 
-        );
-
- 
-        Application {
-            // event_manager: event_manager,
-            // state: state,
-            //app_runner: opt_app_runner,
-        }
+        (state, window_open_options)
     }
 
+    // The setup shared by every way of opening a window. Returns the window options, the handle
+    // for the new window and the closure which creates the window handler.
+    fn prepare(
+        &mut self,
+    ) -> Result<
+        (
+            baseview::WindowOpenOptions,
+            ApplicationHandle,
+            impl FnOnce(&mut baseview::Window) -> OpenWindowExample + Send + 'static,
+        ),
+        FontError,
+    > {
+        // Fail before building anything if the fonts aren't available
+        let font_data = self.fonts.load()?;
+
+        let (mut state, window_open_options) = self.build_state();
+        let (handle, callbacks) = self.take_handle();
+        let input = self.input_handler();
+        let show_damage = self.show_damage;
+
+        // Baseview sends a resize with the scale factor of the window as soon as it's created, and
+        // until then the canvas uses the scale factor from the policy if one was given
+        let scale_factor = match self.scale_policy {
+            WindowScalePolicy::ScaleFactor(scale_factor) => scale_factor,
+            WindowScalePolicy::SystemScaleFactor => 1.0,
+        };
+
+        let width = (window_open_options.size.width * scale_factor).round() as u32;
+        let height = (window_open_options.size.height * scale_factor).round() as u32;
+
+        let handle_state = handle.handle_state.clone();
+
+        let build = move |window: &mut baseview::Window| {
+            let context = gl_context(window);
+            unsafe { context.make_current() };
+            gl::load_with(|symbol| context.get_proc_address(symbol) as *const _);
+            let renderer = OpenGl::new(|symbol| context.get_proc_address(symbol) as *const _).expect("Cannot create renderer");
+            let mut canvas = Canvas::new(renderer).expect("Cannot create canvas");
+            canvas.set_size(width, height, scale_factor as f32);
+
+            // The fonts were parsed when they were loaded, so femtovg accepts them
+            let (fonts, named_fonts) = font_data.register(&mut canvas).expect("Cannot add fonts");
+
+            state.fonts = fonts;
+
+            for (name, font_id) in named_fonts {
+                state.insert_event(Event::new(FontEvent::Registered(name, font_id)).target(Entity::null()));
+            }
+
+            let mut event_manager = EventManager::new();
+            event_manager.clipboard = clipboard::system_clipboard();
+
+            handle_state.open.store(true, Ordering::SeqCst);
+
+            OpenWindowExample {
+                canvas,
+                state,
+                event_manager,
+                input,
+                scale_factor,
+                handle_state,
+                callbacks,
+                renderer: DamageRenderer::new(show_damage),
+            }
+        };
+
+        Ok((window_open_options, handle, build))
+    }
 }
@@ -0,0 +1,231 @@
+use std::sync::mpsc::{channel, Receiver};
+
+#[cfg(target_os = "linux")]
+use std::fmt;
+#[cfg(target_os = "linux")]
+use std::sync::mpsc::Sender;
+#[cfg(target_os = "linux")]
+use std::time::Duration;
+
+/// Sent between widgets and the window to copy and paste text.
+///
+/// `Copy`, `Cut` and `Paste` are sent to the focused entity when Ctrl+C, Ctrl+X and Ctrl+V are
+/// pressed. Widgets put text on the clipboard by sending `SetText`, and can ask for the clipboard
+/// contents at any time by sending `Request`, which is answered with `Paste` sent to the origin
+/// of the request.
+///
+/// Widgets should also send `SetPrimary` whenever their text selection changes. Clicking the middle
+/// mouse button then pastes it into the hovered entity, after its `MouseDown`, on Linux.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClipboardEvent {
+    /// Copy the selection by sending it with `SetText`
+    Copy,
+    /// Copy the selection by sending it with `SetText`, then remove it
+    Cut,
+    /// Put text on the clipboard
+    SetText(String),
+    /// Ask for the text on the clipboard. The system clipboard is read without blocking the
+    /// window, so the `Paste` can arrive a few frames later.
+    Request,
+    /// Insert the text from the clipboard
+    Paste(String),
+    /// Make the selected text the primary selection
+    SetPrimary(String),
+    /// Ask for the primary selection, which is also answered with `Paste`
+    RequestPrimary,
+}
+
+/// One of the selections text can be copied to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Selection {
+    Clipboard,
+    /// The primary selection, which only exists on X11
+    Primary,
+}
+
+/// Where copied text is stored
+pub trait ClipboardProvider {
+    fn get_text(&mut self) -> Option<String>;
+    fn set_text(&mut self, text: String);
+
+    /// The primary selection, which only exists on X11. Other clipboards don't have one by default.
+    fn get_primary_text(&mut self) -> Option<String> {
+        None
+    }
+
+    fn set_primary_text(&mut self, _text: String) {}
+
+    /// Read a selection to answer a `Request`, sending the text through the returned receiver once
+    /// it has been read. Clipboards which have to wait for the text, e.g. for another application to
+    /// send it, should read it without blocking. By default the selection is read straight away.
+    fn request_text(&mut self, selection: Selection) -> Receiver<Option<String>> {
+        let (sender, receiver) = channel();
+
+        let text = match selection {
+            Selection::Clipboard => self.get_text(),
+            Selection::Primary => self.get_primary_text(),
+        };
+
+        // The receiver is still alive
+        let _ = sender.send(text);
+
+        receiver
+    }
+}
+
+/// A clipboard which only exists within one window, used for tests and when the system clipboard
+/// isn't available
+#[derive(Debug, Clone, Default)]
+pub struct MemoryClipboard {
+    text: Option<String>,
+    primary: Option<String>,
+}
+
+impl MemoryClipboard {
+    pub fn new() -> Self {
+        MemoryClipboard::default()
+    }
+}
+
+impl ClipboardProvider for MemoryClipboard {
+    fn get_text(&mut self) -> Option<String> {
+        self.text.clone()
+    }
+
+    fn set_text(&mut self, text: String) {
+        self.text = Some(text);
+    }
+
+    fn get_primary_text(&mut self) -> Option<String> {
+        self.primary.clone()
+    }
+
+    fn set_primary_text(&mut self, text: String) {
+        self.primary = Some(text);
+    }
+}
+
+/// The X11 clipboard couldn't be connected to
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+pub struct ClipboardError(x11_clipboard::error::Error);
+
+#[cfg(target_os = "linux")]
+impl fmt::Display for ClipboardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "cannot connect to the X11 clipboard: {}", self.0)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl std::error::Error for ClipboardError {}
+
+// A request for the text of a selection and where to send it
+#[cfg(target_os = "linux")]
+type ReadRequest = (Selection, Sender<Option<String>>);
+
+/// The X11 CLIPBOARD and PRIMARY selections.
+///
+/// Reading a selection owned by another application waits for that application to send it, for up
+/// to half a second. A `Request` or `RequestPrimary`, e.g. from Ctrl+V or a middle click, is read on
+/// a thread of its own, so the window isn't blocked while waiting. `get_text` and
+/// `get_primary_text` still block.
+#[cfg(target_os = "linux")]
+pub struct X11Clipboard {
+    clipboard: x11_clipboard::Clipboard,
+    // Requests for the thread which reads selections with a connection of its own
+    reads: Sender<ReadRequest>,
+}
+
+#[cfg(target_os = "linux")]
+impl X11Clipboard {
+    // Another application can take a while to answer, but a stuck one shouldn't freeze the window
+    const TIMEOUT: Duration = Duration::from_millis(500);
+
+    pub fn new() -> Result<Self, ClipboardError> {
+        let clipboard = x11_clipboard::Clipboard::new().map_err(ClipboardError)?;
+        let reader = x11_clipboard::Clipboard::new().map_err(ClipboardError)?;
+
+        let (reads, requests) = channel::<ReadRequest>();
+
+        // Ends when the clipboard is dropped along with the sending side
+        std::thread::spawn(move || {
+            for (selection, text) in requests {
+                // Whoever asked may be gone by the time the text arrives
+                let _ = text.send(Self::load(&reader, selection));
+            }
+        });
+
+        Ok(X11Clipboard { clipboard, reads })
+    }
+
+    fn load(clipboard: &x11_clipboard::Clipboard, selection: Selection) -> Option<String> {
+        let atoms = &clipboard.getter.atoms;
+
+        let selection = match selection {
+            Selection::Clipboard => atoms.clipboard,
+            Selection::Primary => atoms.primary,
+        };
+
+        let data = clipboard
+            .load(selection, atoms.utf8_string, atoms.property, Self::TIMEOUT)
+            .ok()?;
+
+        String::from_utf8(data).ok()
+    }
+
+    // Takes ownership of the selection, with the text served to other applications from the
+    // clipboard's own thread until something else takes it
+    fn store(&self, selection: Selection, text: String) {
+        let atoms = &self.clipboard.setter.atoms;
+
+        let selection = match selection {
+            Selection::Clipboard => atoms.clipboard,
+            Selection::Primary => atoms.primary,
+        };
+
+        // Only fails when the connection to the X server is lost, which leaves the selection as it
+        // was and nothing else to do
+        let _ = self.clipboard.store(selection, atoms.utf8_string, text);
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl ClipboardProvider for X11Clipboard {
+    fn get_text(&mut self) -> Option<String> {
+        Self::load(&self.clipboard, Selection::Clipboard)
+    }
+
+    fn set_text(&mut self, text: String) {
+        self.store(Selection::Clipboard, text);
+    }
+
+    fn get_primary_text(&mut self) -> Option<String> {
+        Self::load(&self.clipboard, Selection::Primary)
+    }
+
+    fn set_primary_text(&mut self, text: String) {
+        self.store(Selection::Primary, text);
+    }
+
+    fn request_text(&mut self, selection: Selection) -> Receiver<Option<String>> {
+        let (text, receiver) = channel();
+
+        // The reading thread lives as long as the clipboard
+        let _ = self.reads.send((selection, text));
+
+        receiver
+    }
+}
+
+/// The system clipboard, falling back to a clipboard within the window if it isn't available
+pub fn system_clipboard() -> Box<dyn ClipboardProvider> {
+    #[cfg(target_os = "linux")]
+    {
+        if let Ok(clipboard) = X11Clipboard::new() {
+            return Box::new(clipboard);
+        }
+    }
+
+    Box::new(MemoryClipboard::new())
+}
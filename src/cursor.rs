@@ -0,0 +1,485 @@
+use tuix::{CursorIcon, Entity, State};
+
+use baseview::MouseCursor;
+
+/// The baseview cursor shown for a tuix cursor icon
+pub fn to_mouse_cursor(icon: CursorIcon) -> MouseCursor {
+    match icon {
+        CursorIcon::Arrow => MouseCursor::Default,
+        CursorIcon::Text => MouseCursor::Text,
+        CursorIcon::Hand => MouseCursor::Hand,
+        CursorIcon::Crosshair => MouseCursor::Crosshair,
+        CursorIcon::NotAllowed => MouseCursor::NotAllowed,
+        CursorIcon::Hidden => MouseCursor::Hidden,
+        CursorIcon::NResize => MouseCursor::NResize,
+        CursorIcon::EResize => MouseCursor::EResize,
+        CursorIcon::SResize => MouseCursor::SResize,
+        CursorIcon::WResize => MouseCursor::WResize,
+        CursorIcon::NeResize => MouseCursor::NeResize,
+        CursorIcon::NwResize => MouseCursor::NwResize,
+        CursorIcon::SeResize => MouseCursor::SeResize,
+        CursorIcon::SwResize => MouseCursor::SwResize,
+        CursorIcon::EwResize => MouseCursor::EwResize,
+        CursorIcon::NsResize => MouseCursor::NsResize,
+        CursorIcon::NeswResize => MouseCursor::NeswResize,
+        CursorIcon::NwseResize => MouseCursor::NwseResize,
+    }
+}
+
+/// The cursor for a value of the CSS `cursor` property
+pub fn parse_cursor(value: &str) -> Option<CursorIcon> {
+    let icon = match value {
+        "default" | "auto" => CursorIcon::Arrow,
+        "text" => CursorIcon::Text,
+        "pointer" => CursorIcon::Hand,
+        "crosshair" => CursorIcon::Crosshair,
+        "not-allowed" => CursorIcon::NotAllowed,
+        "none" => CursorIcon::Hidden,
+        "n-resize" => CursorIcon::NResize,
+        "e-resize" => CursorIcon::EResize,
+        "s-resize" => CursorIcon::SResize,
+        "w-resize" => CursorIcon::WResize,
+        "ne-resize" => CursorIcon::NeResize,
+        "nw-resize" => CursorIcon::NwResize,
+        "se-resize" => CursorIcon::SeResize,
+        "sw-resize" => CursorIcon::SwResize,
+        "ew-resize" | "col-resize" => CursorIcon::EwResize,
+        "ns-resize" | "row-resize" => CursorIcon::NsResize,
+        "nesw-resize" => CursorIcon::NeswResize,
+        "nwse-resize" => CursorIcon::NwseResize,
+        _ => return None,
+    };
+
+    Some(icon)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Combinator {
+    // Any ancestor, written as whitespace
+    Descendant,
+    // The parent, written as `>`
+    Child,
+}
+
+// A selector without combinators, e.g. `slider.big:hover`
+#[derive(Debug, Clone, Default)]
+struct Compound {
+    element: Option<String>,
+    classes: Vec<String>,
+    pseudo_classes: Vec<String>,
+}
+
+impl Compound {
+    fn parse(text: &str) -> Option<Compound> {
+        let mut compound = Compound::default();
+
+        // Split before each `.` and `:` while keeping them, so that each part knows what it is
+        let mut parts = Vec::new();
+        let mut start = 0;
+        for (index, chr) in text.char_indices() {
+            if (chr == '.' || chr == ':') && index > start {
+                parts.push(&text[start..index]);
+                start = index;
+            }
+        }
+        parts.push(&text[start..]);
+
+        for part in parts {
+            if let Some(class) = part.strip_prefix('.') {
+                compound.classes.push(class.to_owned());
+            } else if let Some(pseudo_class) = part.strip_prefix(':') {
+                compound.pseudo_classes.push(pseudo_class.to_owned());
+            } else if part.contains('#') || part.contains('[') {
+                // Ids and attributes aren't supported, so the selector can't be matched
+                return None;
+            } else if part != "*" {
+                compound.element = Some(part.to_owned());
+            }
+        }
+
+        Some(compound)
+    }
+
+    fn matches(&self, state: &State, entity: Entity) -> bool {
+        if let Some(element) = &self.element {
+            if state.style.elements.get(entity) != Some(element) {
+                return false;
+            }
+        }
+
+        if !self.classes.is_empty() {
+            match state.style.classes.get(entity) {
+                Some(classes) if self.classes.iter().all(|class| classes.contains(class)) => {}
+                _ => return false,
+            }
+        }
+
+        if !self.pseudo_classes.is_empty() {
+            let pseudo_classes = match state.style.pseudo_classes.get(entity) {
+                Some(pseudo_classes) => pseudo_classes,
+                None => return false,
+            };
+
+            for pseudo_class in self.pseudo_classes.iter() {
+                let matches = match pseudo_class.as_str() {
+                    "hover" => pseudo_classes.get_hover(),
+                    "over" => pseudo_classes.get_over(),
+                    "active" => pseudo_classes.get_active(),
+                    "focus" => pseudo_classes.get_focus(),
+                    "checked" => pseudo_classes.get_checked(),
+                    "disabled" => pseudo_classes.get_disabled(),
+                    _ => false,
+                };
+
+                if !matches {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+}
+
+// A complex selector with the compound selectors from right to left, each with the combinator
+// which relates it to the next one, further to the left
+#[derive(Debug, Clone)]
+struct Selector {
+    compounds: Vec<(Compound, Combinator)>,
+    // Classes and pseudo-classes, then elements, the same as CSS without ids
+    specificity: (usize, usize),
+}
+
+impl Selector {
+    fn parse(text: &str) -> Option<Selector> {
+        let mut compounds = Vec::new();
+        let mut combinator = Combinator::Descendant;
+
+        // Put whitespace around `>` so that the selector can be split on whitespace alone
+        let text = text.replace('>', " > ");
+
+        for token in text.split_whitespace() {
+            if token == ">" {
+                combinator = Combinator::Child;
+                continue;
+            }
+
+            // Each compound keeps the combinator written before it, which relates it to the compound on its left
+            compounds.push((Compound::parse(token)?, combinator));
+            combinator = Combinator::Descendant;
+        }
+
+        if compounds.is_empty() {
+            return None;
+        }
+
+        // Matching starts from the entity itself, which is the rightmost compound
+        compounds.reverse();
+
+        let specificity = compounds.iter().fold((0, 0), |(classes, elements), (compound, _)| {
+            (
+                classes + compound.classes.len() + compound.pseudo_classes.len(),
+                elements + compound.element.is_some() as usize,
+            )
+        });
+
+        Some(Selector { compounds, specificity })
+    }
+
+    fn matches(&self, state: &State, entity: Entity) -> bool {
+        matches_from(&self.compounds, state, entity)
+    }
+}
+
+fn matches_from(compounds: &[(Compound, Combinator)], state: &State, entity: Entity) -> bool {
+    let (compound, combinator) = match compounds.first() {
+        Some(first) => first,
+        None => return true,
+    };
+
+    if !compound.matches(state, entity) {
+        return false;
+    }
+
+    let rest = &compounds[1..];
+
+    if rest.is_empty() {
+        return true;
+    }
+
+    match combinator {
+        Combinator::Child => match state.hierarchy.get_parent(entity) {
+            Some(parent) => matches_from(rest, state, parent),
+            None => false,
+        },
+
+        Combinator::Descendant => {
+            let mut ancestor = state.hierarchy.get_parent(entity);
+
+            while let Some(entity) = ancestor {
+                if matches_from(rest, state, entity) {
+                    return true;
+                }
+
+                ancestor = state.hierarchy.get_parent(entity);
+            }
+
+            false
+        }
+    }
+}
+
+/// The `cursor` properties of the themes and stylesheets in the state, including the themes passed
+/// to the application builder and any the app inserts itself.
+///
+/// Tuix doesn't know about the `cursor` property, so the themes are parsed again for it here.
+/// Selectors can use elements, classes, pseudo-classes and the descendant and child combinators.
+/// Entities without a matching rule use the cursor of their closest ancestor which has one.
+#[derive(Debug, Clone, Default)]
+pub struct CursorStyle {
+    rules: Vec<(Selector, CursorIcon)>,
+    // The number of themes and stylesheets in the state the rules were parsed from
+    sources: (usize, usize),
+}
+
+impl CursorStyle {
+    /// Parse the themes inserted into the state and the stylesheets added to it
+    pub fn from_state(state: &State) -> Self {
+        let resources = &state.resource_manager;
+
+        // Stylesheets are kept as paths, which tuix reads again whenever styles are reloaded
+        let stylesheets = resources.stylesheets.iter().filter_map(|path| std::fs::read_to_string(path).ok());
+        let themes: Vec<String> = stylesheets.chain(resources.themes.iter().cloned()).collect();
+
+        CursorStyle {
+            sources: (resources.themes.len(), resources.stylesheets.len()),
+            ..CursorStyle::parse(&themes)
+        }
+    }
+
+    /// Whether themes or stylesheets were added to the state since the rules were parsed from it
+    pub fn is_outdated(&self, state: &State) -> bool {
+        self.sources != (state.resource_manager.themes.len(), state.resource_manager.stylesheets.len())
+    }
+
+    pub fn parse<S: AsRef<str>>(themes: &[S]) -> Self {
+        let mut rules = Vec::new();
+
+        for theme in themes {
+            let theme = strip_comments(theme.as_ref());
+
+            for block in theme.split('}') {
+                let (selectors, declarations) = match block.find('{') {
+                    Some(index) => (&block[..index], &block[index + 1..]),
+                    None => continue,
+                };
+
+                let cursor = declarations
+                    .split(';')
+                    .filter_map(|declaration| {
+                        let (name, value) = declaration.split_at(declaration.find(':')?);
+                        if name.trim() != "cursor" {
+                            return None;
+                        }
+
+                        parse_cursor(value[1..].trim().trim_end_matches("!important").trim())
+                    })
+                    .last();
+
+                if let Some(cursor) = cursor {
+                    for selector in selectors.split(',').filter_map(Selector::parse) {
+                        rules.push((selector, cursor));
+                    }
+                }
+            }
+        }
+
+        CursorStyle { rules, sources: (0, 0) }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The cursor for an entity from its own rules or those of its ancestors
+    pub fn cursor(&self, state: &State, entity: Entity) -> Option<CursorIcon> {
+        let mut current = Some(entity);
+
+        while let Some(entity) = current {
+            if entity == Entity::null() {
+                break;
+            }
+
+            // The most specific rule wins, with later rules winning between equal ones
+            let cursor = self
+                .rules
+                .iter()
+                .enumerate()
+                .filter(|(_, (selector, _))| selector.matches(state, entity))
+                .max_by_key(|(index, (selector, _))| (selector.specificity, *index))
+                .map(|(_, (_, cursor))| *cursor);
+
+            if cursor.is_some() {
+                return cursor;
+            }
+
+            current = state.hierarchy.get_parent(entity);
+        }
+
+        None
+    }
+}
+
+fn strip_comments(theme: &str) -> String {
+    let mut stripped = String::with_capacity(theme.len());
+    let mut rest = theme;
+
+    while let Some(start) = rest.find("/*") {
+        stripped.push_str(&rest[..start]);
+        rest = match rest[start + 2..].find("*/") {
+            Some(end) => &rest[start + 2 + end + 2..],
+            None => "",
+        };
+    }
+
+    stripped.push_str(rest);
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tuix::widgets::Element;
+    use tuix::window::WindowWidget;
+    use tuix::BuildHandler;
+
+    fn test_state() -> State {
+        let mut state = State::new();
+
+        WindowWidget::new().build_window(&mut state);
+        state.hierarchy.add(state.root, None);
+
+        state
+    }
+
+    fn add_element(state: &mut State, parent: Entity, element: &str, class: &str) -> Entity {
+        Element::new().build(state, parent, |builder| builder.set_element(element).class(class))
+    }
+
+    #[test]
+    fn strips_comments() {
+        assert_eq!(strip_comments("a /* b */ c"), "a  c");
+        assert_eq!(strip_comments("/* a */b/* c */d"), "bd");
+        // An unterminated comment runs to the end
+        assert_eq!(strip_comments("a /* b"), "a ");
+        assert_eq!(strip_comments("no comments"), "no comments");
+    }
+
+    #[test]
+    fn parses_selectors_from_right_to_left() {
+        let selector = Selector::parse("knob.big > .tick:hover").unwrap();
+
+        assert_eq!(selector.compounds.len(), 2);
+
+        let (tick, combinator) = &selector.compounds[0];
+        assert_eq!(tick.element, None);
+        assert_eq!(tick.classes, vec!["tick"]);
+        assert_eq!(tick.pseudo_classes, vec!["hover"]);
+        assert_eq!(*combinator, Combinator::Child);
+
+        let (knob, combinator) = &selector.compounds[1];
+        assert_eq!(knob.element.as_deref(), Some("knob"));
+        assert_eq!(knob.classes, vec!["big"]);
+        assert_eq!(*combinator, Combinator::Descendant);
+
+        assert_eq!(selector.specificity, (3, 1));
+    }
+
+    #[test]
+    fn rejects_unsupported_selectors() {
+        assert!(Selector::parse("#id").is_none());
+        assert!(Selector::parse("button[disabled]").is_none());
+        assert!(Selector::parse("  ").is_none());
+    }
+
+    #[test]
+    fn matches_elements_classes_and_combinators() {
+        let mut state = test_state();
+        let root = state.root;
+        let knob = add_element(&mut state, root, "knob", "big");
+        let back = add_element(&mut state, knob, "element", "back");
+        let tick = add_element(&mut state, back, "element", "tick");
+
+        let matches = |selector: &str, entity: Entity| Selector::parse(selector).unwrap().matches(&state, entity);
+
+        assert!(matches("knob", knob));
+        assert!(matches("knob.big", knob));
+        assert!(!matches("knob.small", knob));
+        assert!(matches("*", tick));
+
+        assert!(matches("knob .tick", tick));
+        assert!(matches("knob > .back", back));
+        // The knob is the grandparent of the tick, not its parent
+        assert!(!matches("knob > .tick", tick));
+        assert!(matches("knob > .back > .tick", tick));
+        assert!(!matches(".tick .back", tick));
+    }
+
+    #[test]
+    fn matches_pseudo_classes() {
+        let mut state = test_state();
+        let root = state.root;
+        let knob = add_element(&mut state, root, "knob", "big");
+
+        let selector = Selector::parse("knob:hover").unwrap();
+        assert!(!selector.matches(&state, knob));
+
+        state.style.pseudo_classes.get_mut(knob).unwrap().set_hover(true);
+        assert!(selector.matches(&state, knob));
+    }
+
+    #[test]
+    fn the_most_specific_rule_wins() {
+        let mut state = test_state();
+        let root = state.root;
+        let knob = add_element(&mut state, root, "knob", "big");
+        let tick = add_element(&mut state, knob, "element", "tick");
+
+        let style = CursorStyle::parse(&[
+            "knob.big { cursor: ns-resize; }",
+            "/* later, but less specific */ knob { cursor: pointer; }",
+            "knob:active { cursor: grab; cursor: crosshair; }",
+        ]);
+
+        assert_eq!(style.cursor(&state, knob), Some(CursorIcon::NsResize));
+        // Inherited from the knob
+        assert_eq!(style.cursor(&state, tick), Some(CursorIcon::NsResize));
+        assert_eq!(style.cursor(&state, root), None);
+
+        // Equally specific rules are won by the later one
+        state.style.pseudo_classes.get_mut(knob).unwrap().set_active(true);
+        assert_eq!(style.cursor(&state, knob), Some(CursorIcon::Crosshair));
+
+        let style = CursorStyle::parse(&["knob { cursor: pointer; }", "knob { cursor: text; }"]);
+        assert_eq!(style.cursor(&state, knob), Some(CursorIcon::Text));
+    }
+
+    #[test]
+    fn themes_inserted_into_the_state_are_parsed() {
+        let mut state = test_state();
+        let root = state.root;
+        let knob = add_element(&mut state, root, "knob", "big");
+
+        let style = CursorStyle::from_state(&state);
+        assert_eq!(style.cursor(&state, knob), None);
+        assert!(!style.is_outdated(&state));
+
+        state.insert_theme("knob { cursor: pointer; }");
+        assert!(style.is_outdated(&state));
+
+        let style = CursorStyle::from_state(&state);
+        assert_eq!(style.cursor(&state, knob), Some(CursorIcon::Hand));
+        assert!(!style.is_outdated(&state));
+    }
+}
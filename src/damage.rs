@@ -0,0 +1,226 @@
+use tuix::{Entity, State};
+
+use crate::hit_test::border_width;
+
+/// An axis aligned rectangle in logical pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Rect { x, y, w, h }
+    }
+
+    // The area drawn by an entity, grown to include the outer half of the border and antialiasing
+    pub fn from_entity(state: &State, entity: Entity) -> Self {
+        let margin = border_width(state, entity) / 2.0 + 1.0;
+
+        Rect {
+            x: state.transform.get_posx(entity) - margin,
+            y: state.transform.get_posy(entity) - margin,
+            w: state.transform.get_width(entity) + 2.0 * margin,
+            h: state.transform.get_height(entity) + 2.0 * margin,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.w <= 0.0 || self.h <= 0.0
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.x + other.w
+            && other.x < self.x + self.w
+            && self.y < other.y + other.h
+            && other.y < self.y + self.h
+    }
+
+    pub fn union(&self, other: &Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+
+        Rect {
+            x,
+            y,
+            w: (self.x + self.w).max(other.x + other.w) - x,
+            h: (self.y + self.h).max(other.y + other.h) - y,
+        }
+    }
+
+    /// Scale to physical pixels, rounding outwards to whole pixels
+    pub fn to_physical(&self, scale_factor: f32) -> (u32, u32, u32, u32) {
+        let x = (self.x * scale_factor).floor().max(0.0);
+        let y = (self.y * scale_factor).floor().max(0.0);
+        let right = ((self.x + self.w) * scale_factor).ceil().max(x);
+        let bottom = ((self.y + self.h) * scale_factor).ceil().max(y);
+
+        (x as u32, y as u32, (right - x) as u32, (bottom - y) as u32)
+    }
+}
+
+/// The parts of the window which need to be drawn again
+#[derive(Debug, Clone, Default)]
+pub struct DamageRegion {
+    rects: Vec<Rect>,
+    full: bool,
+}
+
+impl DamageRegion {
+    /// Mark the whole window as damaged
+    pub fn add_full(&mut self) {
+        self.full = true;
+        self.rects.clear();
+    }
+
+    /// Mark a rectangle as damaged, merging it with any damaged rectangles it overlaps
+    pub fn add(&mut self, rect: Rect) {
+        if self.full || rect.is_empty() {
+            return;
+        }
+
+        let mut rect = rect;
+
+        // Merging can make the rectangle overlap ones which were checked before, so repeat until stable
+        loop {
+            let before = self.rects.len();
+            self.rects.retain(|other| {
+                if other.intersects(&rect) {
+                    rect = rect.union(other);
+                    false
+                } else {
+                    true
+                }
+            });
+
+            if self.rects.len() == before {
+                break;
+            }
+        }
+
+        self.rects.push(rect);
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.full
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.full && self.rects.is_empty()
+    }
+
+    /// The damaged rectangles, with a full damage region resolved to the window bounds
+    pub fn rects(&self, window: Rect) -> Vec<Rect> {
+        if self.full {
+            vec![window]
+        } else {
+            self.rects.clone()
+        }
+    }
+
+    pub fn intersects(&self, rect: &Rect) -> bool {
+        self.full || self.rects.iter().any(|damaged| damaged.intersects(rect))
+    }
+
+    pub fn clear(&mut self) {
+        self.full = false;
+        self.rects.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window() -> Rect {
+        Rect::new(0.0, 0.0, 200.0, 100.0)
+    }
+
+    #[test]
+    fn overlapping_rects_are_merged() {
+        let mut damage = DamageRegion::default();
+
+        damage.add(Rect::new(0.0, 0.0, 10.0, 10.0));
+        damage.add(Rect::new(50.0, 50.0, 10.0, 10.0));
+        assert_eq!(damage.rects(window()).len(), 2);
+
+        damage.add(Rect::new(5.0, 5.0, 10.0, 10.0));
+        assert_eq!(
+            damage.rects(window()),
+            vec![Rect::new(50.0, 50.0, 10.0, 10.0), Rect::new(0.0, 0.0, 15.0, 15.0)]
+        );
+    }
+
+    #[test]
+    fn merging_repeats_until_no_rects_overlap() {
+        let mut damage = DamageRegion::default();
+
+        damage.add(Rect::new(0.0, 0.0, 10.0, 10.0));
+        damage.add(Rect::new(15.0, 0.0, 10.0, 30.0));
+
+        // Overlaps only the second, but the union with it covers the first as well
+        damage.add(Rect::new(0.0, 20.0, 20.0, 10.0));
+
+        assert_eq!(damage.rects(window()), vec![Rect::new(0.0, 0.0, 25.0, 30.0)]);
+    }
+
+    #[test]
+    fn full_damage_ignores_rects() {
+        let mut damage = DamageRegion::default();
+
+        damage.add(Rect::new(0.0, 0.0, 10.0, 10.0));
+        damage.add_full();
+        damage.add(Rect::new(50.0, 50.0, 10.0, 10.0));
+
+        assert!(damage.is_full());
+        assert_eq!(damage.rects(window()), vec![window()]);
+
+        damage.clear();
+        assert!(damage.is_empty());
+    }
+
+    #[test]
+    fn empty_rects_are_not_damage() {
+        let mut damage = DamageRegion::default();
+
+        damage.add(Rect::new(10.0, 10.0, 0.0, 10.0));
+        damage.add(Rect::new(10.0, 10.0, 10.0, -1.0));
+
+        assert!(damage.is_empty());
+    }
+
+    #[test]
+    fn touching_rects_do_not_intersect() {
+        let rect = Rect::new(10.0, 10.0, 10.0, 10.0);
+
+        assert!(rect.intersects(&Rect::new(15.0, 15.0, 10.0, 10.0)));
+        assert!(rect.intersects(&Rect::new(12.0, 12.0, 2.0, 2.0)));
+        assert!(!rect.intersects(&Rect::new(20.0, 10.0, 10.0, 10.0)));
+        assert!(!rect.intersects(&Rect::new(10.0, 20.0, 10.0, 10.0)));
+
+        let mut damage = DamageRegion::default();
+        assert!(!damage.intersects(&rect));
+
+        damage.add(Rect::new(0.0, 0.0, 11.0, 11.0));
+        assert!(damage.intersects(&rect));
+        assert!(!damage.intersects(&Rect::new(11.0, 0.0, 5.0, 5.0)));
+    }
+
+    #[test]
+    fn physical_rects_are_rounded_outwards() {
+        assert_eq!(Rect::new(1.0, 2.0, 3.0, 4.0).to_physical(1.0), (1, 2, 3, 4));
+        assert_eq!(Rect::new(1.0, 2.0, 3.0, 4.0).to_physical(2.0), (2, 4, 6, 8));
+
+        // 1.5..4.5 by 0.25..0.75 covers the pixels from 1 to 5 and from 0 to 1
+        assert_eq!(Rect::new(1.5, 0.25, 3.0, 0.5).to_physical(1.0), (1, 0, 4, 1));
+        assert_eq!(Rect::new(0.4, 0.4, 1.0, 1.0).to_physical(1.5), (0, 0, 3, 3));
+    }
+
+    #[test]
+    fn physical_rects_are_clamped_to_the_window_origin() {
+        assert_eq!(Rect::new(-2.5, -1.0, 5.0, 3.0).to_physical(1.0), (0, 0, 3, 2));
+    }
+}
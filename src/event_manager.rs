@@ -2,10 +2,15 @@ use tuix::{
     BuildHandler, Builder, Entity, Event, EventHandler, Hierarchy, HierarchyTree,
     IntoHierarchyIterator, IntoParentIterator, State, WidgetEvent, Window, WindowEvent, CursorIcon
 };
+use tuix::events::Propagation;
 use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::{Receiver, TryRecvError};
 
 use std::time::{Duration, Instant};
 
+use crate::clipboard::{ClipboardEvent, ClipboardProvider, MemoryClipboard, Selection};
+use crate::damage::{DamageRegion, Rect};
+
 
 use femtovg::{
     renderer::OpenGl,
@@ -31,6 +36,22 @@ pub struct EventManager {
     needs_redraw: bool,
     total_frames: usize,
     pub start_time: std::time::Instant,
+    // The areas which need to be drawn again, accumulated until the next draw
+    pub damage: DamageRegion,
+    // The bounds of each entity when it was last drawn, used to find what moved after a relayout
+    drawn_bounds: HashMap<Entity, Rect>,
+    // The last cursor requested with `WindowEvent::SetCursor` which hasn't been applied yet
+    pub cursor: Option<CursorIcon>,
+    // Whether a handler consumed any of the events translated from the input in the last
+    // `flush_input`, other than redraw, relayout and restyle events
+    pub consumed: bool,
+    // Whether a relayout was handled since the input handler last checked. The hierarchy and
+    // z-orders only change along with a relayout, so this invalidates the hit test cache.
+    pub relayout_handled: bool,
+    // Where `ClipboardEvent`s copy to and paste from
+    pub clipboard: Box<dyn ClipboardProvider>,
+    // Clipboard requests still being read, with the entity to send the text to
+    clipboard_reads: Vec<(Entity, Receiver<Option<String>>)>,
 }
 
 impl EventManager {
@@ -41,6 +62,13 @@ impl EventManager {
             needs_redraw: false,
             total_frames: 0,
             start_time: std::time::Instant::now(),
+            damage: DamageRegion::default(),
+            drawn_bounds: HashMap::new(),
+            cursor: None,
+            consumed: false,
+            relayout_handled: false,
+            clipboard: Box::new(MemoryClipboard::new()),
+            clipboard_reads: Vec::new(),
         }
     }
 
@@ -49,9 +77,38 @@ impl EventManager {
     // }
 
     pub fn flush_events(&mut self, state: &mut State) -> bool {
+        self.flush(state, None)
+    }
+
+    /// Flush the events queued in the state, where the events from `first_input` on were
+    /// translated from an input event. Only handlers consuming those set `consumed`, not
+    /// handlers of events which happened to be queued along with them.
+    pub fn flush_input(&mut self, state: &mut State, first_input: usize) -> bool {
+        self.flush(state, Some(first_input))
+    }
+
+    /// Advance animations and handle the queued events, which a window does once per frame
+    /// before drawing
+    pub fn update(&mut self, state: &mut State) -> bool {
+        // Answer the clipboard requests which have been read since the last frame
+        let mut reads = std::mem::take(&mut self.clipboard_reads);
+        reads.retain(|(origin, text)| !send_paste(state, *origin, text));
+        self.clipboard_reads.append(&mut reads);
+
+        if state.apply_animations() {
+            state.insert_event(Event::new(WindowEvent::Relayout).target(Entity::null()).origin(state.root));
+            state.insert_event(Event::new(WindowEvent::Redraw).target(Entity::null()).origin(state.root));
+        }
+
+        self.flush_events(state)
+    }
+
+    fn flush(&mut self, state: &mut State, first_input: Option<usize>) -> bool {
         //println!("FLUSH");
 
-        let mut needs_redraw = false;
+        self.needs_redraw = false;
+        self.consumed = false;
+        let mut check_bounds = false;
 
         // Copy the hierarchy from state
         let hierarchy = state.hierarchy.clone();
@@ -66,48 +123,96 @@ impl EventManager {
         // Move event handlers from state to event manager
         self.event_handlers.extend(state.event_handlers.drain());
 
-        // Move events from state into event manager
+        // Move events from state into event manager, noting which were translated from the input
+        // before they are sorted
         let event_queue = state.event_queue.clone();
         //self.event_queue.append(&mut state.event_queue);
-        self.event_queue = event_queue.into_iter().collect::<Vec<Event>>();
+        let mut events = event_queue
+            .into_iter()
+            .enumerate()
+            .map(|(index, event)| (event, first_input.map_or(false, |first| index >= first)))
+            .collect::<Vec<(Event, bool)>>();
 
-        self.event_queue.sort_by_cached_key(|event| event.order);
+        events.sort_by_cached_key(|(event, _)| event.order);
+
+        let (event_queue, from_input): (Vec<Event>, Vec<bool>) = events.into_iter().unzip();
+        self.event_queue = event_queue;
 
         // Clear the event queue in state
         state.event_queue.clear();
 
         // Loop over the events in the event manager queue
-        'events: for event in self.event_queue.iter_mut() {
+        'events: for (event, from_input) in self.event_queue.iter_mut().zip(from_input) {
             //println!("Event: {:?}", event);
-            // If a redraw is needed then set the flag to return true
-            /*
+
+            // A handler returning true for the window's own bookkeeping events doesn't mean any input was used
+            let is_input = from_input && match event.message.downcast::<WindowEvent>() {
+                Some(WindowEvent::Redraw) | Some(WindowEvent::Relayout) | Some(WindowEvent::Restyle) => false,
+                _ => true,
+            };
+
+            // Anything which changes the style, layout or appearance requires the window to be drawn again
             if let Some(window_event) = event.message.downcast::<WindowEvent>() {
                 match window_event {
                     WindowEvent::Redraw => {
-                        needs_redraw = true;
+                        self.needs_redraw = true;
+
+                        // A redraw without a specific entity damages the whole window
+                        if event.target == Entity::null() || event.target == state.root {
+                            self.damage.add_full();
+                        } else {
+                            damage_entity(&mut self.damage, &self.drawn_bounds, state, event.target);
+                        }
                     }
 
+                    // Applied by the window after flushing, since only it can change the cursor
                     WindowEvent::SetCursor(cursor_icon) => {
-                        match cursor_icon {
-                            CursorIcon::Arrow => {
-                                window.handle.window().set_cursor_icon(glutin::window::CursorIcon::Arrow);
-                            }
-
-                            CursorIcon::NResize => {
-                                window.handle.window().set_cursor_icon(glutin::window::CursorIcon::NResize);
-                            }
-
-                            CursorIcon::EResize => {
-                                window.handle.window().set_cursor_icon(glutin::window::CursorIcon::EResize);
-                            }
+                        self.cursor = Some(*cursor_icon);
+                    }
+
+                    WindowEvent::Relayout | WindowEvent::Restyle => {
+                        self.needs_redraw = true;
+                        check_bounds = true;
+                        self.relayout_handled |= matches!(window_event, WindowEvent::Relayout);
+
+                        if event.origin != Entity::null() && event.origin != state.root {
+                            damage_entity(&mut self.damage, &self.drawn_bounds, state, event.origin);
+                        } else if let WindowEvent::Restyle = window_event {
+                            // Any entity may look different without changing its bounds, e.g. from
+                            // a pseudo-class like `:active`, so the whole window is drawn again
+                            self.damage.add_full();
+                        }
+                    }
+
+                    _ => {}
+                }
+            }
+
+            if let Some(clipboard_event) = event.message.downcast::<ClipboardEvent>() {
+                match clipboard_event {
+                    ClipboardEvent::SetText(text) => self.clipboard.set_text(text.clone()),
+
+                    ClipboardEvent::SetPrimary(text) => self.clipboard.set_primary_text(text.clone()),
+
+                    ClipboardEvent::Request | ClipboardEvent::RequestPrimary => {
+                        let selection = if *clipboard_event == ClipboardEvent::Request {
+                            Selection::Clipboard
+                        } else {
+                            Selection::Primary
+                        };
+
+                        let text = self.clipboard.request_text(selection);
+
+                        // Text which is available straight away is pasted as part of this flush,
+                        // anything else once it has been read
+                        if !send_paste(state, event.origin, &text) {
+                            self.clipboard_reads.push((event.origin, text));
                         }
-                    
                     }
 
                     _ => {}
                 }
             }
-            */
 
             let target = event.target;
 
@@ -117,6 +222,7 @@ impl EventManager {
                     //println!("Entity: {}", entity);
                     if let Some(event_handler) = self.event_handlers.get_mut(&entity) {
                         if event_handler.on_event(state, *entity, event) {
+                            self.consumed |= is_input;
                             break;
                         }
                     }
@@ -137,6 +243,7 @@ impl EventManager {
                     // Send event to all entities before the target
                     if let Some(event_handler) = self.event_handlers.get_mut(&entity) {
                         if event_handler.on_event(state, entity, event) {
+                            self.consumed |= is_input;
                             continue 'events;
                         }
                     }
@@ -146,6 +253,7 @@ impl EventManager {
             // Send event to target
             if let Some(event_handler) = self.event_handlers.get_mut(&event.target) {
                 if event_handler.on_event(state, event.target, event) {
+                    self.consumed |= is_input;
                     continue 'events;
                 }
             }
@@ -162,6 +270,7 @@ impl EventManager {
                     // Send event to all entities before the target
                     if let Some(event_handler) = self.event_handlers.get_mut(&entity) {
                         if event_handler.on_event(state, entity, event) {
+                            self.consumed |= is_input;
                             continue 'events;
                         }
                     }
@@ -180,6 +289,7 @@ impl EventManager {
                     
                     if let Some(event_handler) = self.event_handlers.get_mut(&widget) {
                         if event_handler.on_event(state, widget, event) {
+                            self.consumed |= is_input;
                             continue 'events;
                         }
                     }
@@ -187,10 +297,38 @@ impl EventManager {
             }
         }
 
-        return needs_redraw;
+        // Damage anything which moved, resized, appeared or disappeared since it was last drawn
+        if check_bounds {
+            let mut remaining = self.drawn_bounds.clone();
+
+            // Entities may have been added or removed while handling events
+            let hierarchy = state.hierarchy.clone();
+
+            for entity in hierarchy.into_iter() {
+                let bounds = Rect::from_entity(state, entity);
+
+                match remaining.remove(&entity) {
+                    Some(drawn) if drawn == bounds => {}
+
+                    Some(drawn) => {
+                        self.damage.add(drawn);
+                        self.damage.add(bounds);
+                    }
+
+                    None => self.damage.add(bounds),
+                }
+            }
+
+            for drawn in remaining.values() {
+                self.damage.add(*drawn);
+            }
+        }
+
+        return self.needs_redraw;
     }
 
-    pub fn draw(&mut self, state: &mut State, hierarchy: &Hierarchy, canvas: &mut Canvas<OpenGl>) {
+    /// Draw the entities which intersect the damaged region
+    pub fn draw(&mut self, state: &mut State, hierarchy: &Hierarchy, canvas: &mut Canvas<OpenGl>, damage: &DamageRegion) {
 
         //let dpi_factor = window.handle.window().scale_factor();
         //let size = window.handle.window().inner_size();
@@ -204,7 +342,16 @@ impl EventManager {
         draw_hierarchy.sort_by_cached_key(|entity| state.transform.get_z_order(*entity));
 
 
+        self.drawn_bounds.clear();
+
         for widget in draw_hierarchy.into_iter() {
+            let bounds = Rect::from_entity(state, widget);
+            self.drawn_bounds.insert(widget, bounds);
+
+            if !damage.intersects(&bounds) {
+                continue;
+            }
+
             if let Some(event_handler) = self.event_handlers.get_mut(&widget) {
                 event_handler.on_draw(state, widget, canvas);
             }
@@ -217,4 +364,33 @@ impl EventManager {
         //     .swap_buffers()
         //     .expect("Failed to swap buffers");
     }
-}
\ No newline at end of file
+}
+
+// Send the text read for a clipboard request to the entity which asked for it, returning whether
+// the request is done, including when there was no text or reading it failed
+fn send_paste(state: &mut State, origin: Entity, text: &Receiver<Option<String>>) -> bool {
+    match text.try_recv() {
+        Ok(Some(text)) => {
+            state.insert_event(
+                Event::new(ClipboardEvent::Paste(text))
+                    .target(origin)
+                    .propagate(Propagation::Direct),
+            );
+
+            true
+        }
+
+        Ok(None) | Err(TryRecvError::Disconnected) => true,
+
+        Err(TryRecvError::Empty) => false,
+    }
+}
+
+// Damage both the current bounds of an entity and where it was last drawn
+fn damage_entity(damage: &mut DamageRegion, drawn_bounds: &HashMap<Entity, Rect>, state: &State, entity: Entity) {
+    if let Some(drawn) = drawn_bounds.get(&entity) {
+        damage.add(*drawn);
+    }
+
+    damage.add(Rect::from_entity(state, entity));
+}
@@ -0,0 +1,226 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::path::PathBuf;
+
+use femtovg::{renderer::OpenGl, Canvas, FontId};
+
+use tuix::state::Fonts;
+
+/// Where to load a font from
+#[derive(Debug, Clone)]
+pub enum FontSource {
+    Memory(Cow<'static, [u8]>),
+    File(PathBuf),
+}
+
+impl FontSource {
+    pub fn memory<D: Into<Cow<'static, [u8]>>>(data: D) -> Self {
+        FontSource::Memory(data.into())
+    }
+
+    pub fn file<P: Into<PathBuf>>(path: P) -> Self {
+        FontSource::File(path.into())
+    }
+
+    // Reads font files and parses them up front so that errors can be reported before a window is
+    // opened. `name` describes the font in errors.
+    fn load(&self, name: &str) -> Result<FontSource, FontError> {
+        let data = match self {
+            FontSource::Memory(data) => data.clone(),
+            FontSource::File(path) => std::fs::read(path)
+                .map(Cow::Owned)
+                .map_err(|err| FontError::Io(path.clone(), err))?,
+        };
+
+        // femtovg uses the first font in a collection
+        if ttf_parser::Font::from_data(&data, 0).is_none() {
+            return Err(FontError::Invalid(self.describe(name)));
+        }
+
+        Ok(FontSource::Memory(data))
+    }
+
+    fn register(&self, canvas: &mut Canvas<OpenGl>, name: &str) -> Result<FontId, FontError> {
+        let font = match self {
+            FontSource::Memory(data) => canvas.add_font_mem(data),
+            FontSource::File(path) => canvas.add_font(path),
+        };
+
+        font.map_err(|_| FontError::Invalid(self.describe(name)))
+    }
+
+    fn describe(&self, name: &str) -> String {
+        match self {
+            FontSource::Memory(_) => format!("the {} font", name),
+            FontSource::File(path) => path.display().to_string(),
+        }
+    }
+}
+
+impl From<&'static [u8]> for FontSource {
+    fn from(data: &'static [u8]) -> Self {
+        FontSource::memory(data)
+    }
+}
+
+impl From<Vec<u8>> for FontSource {
+    fn from(data: Vec<u8>) -> Self {
+        FontSource::memory(data)
+    }
+}
+
+impl From<PathBuf> for FontSource {
+    fn from(path: PathBuf) -> Self {
+        FontSource::file(path)
+    }
+}
+
+/// An error preventing the fonts of a window from being loaded
+#[derive(Debug)]
+pub enum FontError {
+    /// No regular font was provided and the `default-fonts` feature is disabled
+    NoRegularFont,
+    /// A font file couldn't be read
+    Io(PathBuf, std::io::Error),
+    /// A font couldn't be parsed, described by its path or its use
+    Invalid(String),
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FontError::NoRegularFont => write!(
+                f,
+                "no regular font was provided, either set one with `ApplicationBuilder::regular_font` or enable the `default-fonts` feature"
+            ),
+            FontError::Io(path, err) => write!(f, "cannot read font {}: {}", path.display(), err),
+            FontError::Invalid(font) => write!(f, "{} is not a valid TrueType or OpenType font", font),
+        }
+    }
+}
+
+impl std::error::Error for FontError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FontError::NoRegularFont | FontError::Invalid(_) => None,
+            FontError::Io(_, err) => Some(err),
+        }
+    }
+}
+
+/// Sent to every entity once for each named font after it has been registered with the canvas
+#[derive(Debug, Clone, PartialEq)]
+pub enum FontEvent {
+    Registered(String, FontId),
+}
+
+/// The fonts registered with the canvas when a window is opened.
+///
+/// The bundled fonts used by default depend on the `default-fonts`, `icon-font` and `emoji-font`
+/// features. A bold font falls back to the regular font when it isn't provided.
+///
+/// Named fonts can be used by widgets through the ids sent with `FontEvent::Registered`, and are
+/// also used by femtovg as fallbacks for glyphs which are missing from the other fonts.
+#[derive(Debug, Clone)]
+pub struct FontData {
+    pub regular: Option<FontSource>,
+    pub bold: Option<FontSource>,
+    pub icons: Option<FontSource>,
+    pub named: Vec<(String, FontSource)>,
+}
+
+impl Default for FontData {
+    fn default() -> Self {
+        #[allow(unused_mut)]
+        let mut named = Vec::new();
+
+        #[cfg(feature = "emoji-font")]
+        named.push((
+            "emoji".to_owned(),
+            FontSource::memory(&include_bytes!("../resources/NotoEmoji-Regular.ttf")[..]),
+        ));
+
+        FontData {
+            #[cfg(feature = "default-fonts")]
+            regular: Some(FontSource::memory(&include_bytes!("../resources/Roboto-Regular.ttf")[..])),
+            #[cfg(not(feature = "default-fonts"))]
+            regular: None,
+
+            #[cfg(feature = "default-fonts")]
+            bold: Some(FontSource::memory(&include_bytes!("../resources/Roboto-Bold.ttf")[..])),
+            #[cfg(not(feature = "default-fonts"))]
+            bold: None,
+
+            #[cfg(feature = "icon-font")]
+            icons: Some(FontSource::memory(&include_bytes!("../resources/entypo.ttf")[..])),
+            #[cfg(not(feature = "icon-font"))]
+            icons: None,
+
+            named,
+        }
+    }
+}
+
+impl FontData {
+    /// Checks that a regular font is available, reads any font files into memory and checks that
+    /// every font can be parsed
+    pub(crate) fn load(&self) -> Result<FontData, FontError> {
+        let regular = self.regular.as_ref().ok_or(FontError::NoRegularFont)?.load("regular")?;
+
+        Ok(FontData {
+            regular: Some(regular),
+            bold: self.bold.as_ref().map(|source| source.load("bold")).transpose()?,
+            icons: self.icons.as_ref().map(|source| source.load("icon")).transpose()?,
+            named: self
+                .named
+                .iter()
+                .map(|(name, source)| Ok((name.clone(), source.load(name)?)))
+                .collect::<Result<_, FontError>>()?,
+        })
+    }
+
+    pub(crate) fn register(&self, canvas: &mut Canvas<OpenGl>) -> Result<(Fonts, Vec<(String, FontId)>), FontError> {
+        let regular = self.regular.as_ref().map(|source| source.register(canvas, "regular")).transpose()?;
+        let bold = self
+            .bold
+            .as_ref()
+            .map(|source| source.register(canvas, "bold"))
+            .transpose()?
+            .or(regular);
+        let icons = self.icons.as_ref().map(|source| source.register(canvas, "icon")).transpose()?;
+
+        let fonts = Fonts {
+            regular,
+            bold,
+            icons,
+        };
+
+        let named = self
+            .named
+            .iter()
+            .map(|(name, source)| Ok((name.clone(), source.register(canvas, name)?)))
+            .collect::<Result<_, FontError>>()?;
+
+        Ok((fonts, named))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_fonts_are_rejected_when_loaded() {
+        let fonts = FontData {
+            regular: Some(FontSource::memory(&include_bytes!("../resources/Roboto-Regular.ttf")[..])),
+            bold: None,
+            icons: None,
+            named: vec![("broken".to_owned(), FontSource::memory(vec![0, 1, 2, 3]))],
+        };
+
+        match fonts.load() {
+            Err(FontError::Invalid(font)) => assert_eq!(font, "the broken font"),
+            result => panic!("Expected an invalid font, got {:?}", result.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use std::fmt;
+use std::path::Path;
+
+use femtovg::{renderer::OpenGl, Canvas};
+
+use glutin::dpi::PhysicalSize;
+use glutin::{Context, ContextBuilder, CreationError, NotCurrent, PossiblyCurrent};
+
+use image::RgbaImage;
+
+use tuix::window::WindowDescription;
+use tuix::{Entity, State};
+
+use crate::application::ApplicationBuilder;
+use crate::event_manager::EventManager;
+use crate::fonts::{FontError, FontEvent};
+use crate::render::DamageRenderer;
+
+use tuix::events::Event;
+
+/// An error preventing a headless window from being created or rendered
+#[derive(Debug)]
+pub enum HeadlessError {
+    Font(FontError),
+    /// The offscreen GL context couldn't be created, e.g. because OSMesa isn't installed
+    Context(CreationError),
+    Renderer(femtovg::ErrorKind),
+    Image(image::ImageError),
+}
+
+impl fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HeadlessError::Font(err) => write!(f, "{}", err),
+            HeadlessError::Context(err) => write!(f, "cannot create an offscreen GL context: {}", err),
+            HeadlessError::Renderer(err) => write!(f, "rendering failed: {:?}", err),
+            HeadlessError::Image(err) => write!(f, "cannot save image: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError {}
+
+impl From<FontError> for HeadlessError {
+    fn from(err: FontError) -> Self {
+        HeadlessError::Font(err)
+    }
+}
+
+impl From<CreationError> for HeadlessError {
+    fn from(err: CreationError) -> Self {
+        HeadlessError::Context(err)
+    }
+}
+
+impl From<femtovg::ErrorKind> for HeadlessError {
+    fn from(err: femtovg::ErrorKind) -> Self {
+        HeadlessError::Renderer(err)
+    }
+}
+
+impl From<image::ImageError> for HeadlessError {
+    fn from(err: image::ImageError) -> Self {
+        HeadlessError::Image(err)
+    }
+}
+
+// OSMesa renders on the CPU, so no GPU or display server is needed
+#[cfg(target_os = "linux")]
+fn create_context(width: u32, height: u32) -> Result<Context<NotCurrent>, CreationError> {
+    use glutin::platform::unix::HeadlessContextExt;
+
+    ContextBuilder::new().build_osmesa(PhysicalSize::new(width, height))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn create_context(width: u32, height: u32) -> Result<Context<NotCurrent>, CreationError> {
+    let event_loop = glutin::event_loop::EventLoop::new();
+
+    ContextBuilder::new().build_headless(&event_loop, PhysicalSize::new(width, height))
+}
+
+/// A tuix window without a platform window, which renders into an offscreen GL context.
+///
+/// Events are handled and widgets drawn the same way as in a baseview window, including drawing only
+/// the damaged parts of the window, so this can be used
+/// to test widget trees or take screenshots on machines without a GPU.
+pub struct HeadlessWindow {
+    // The canvas is declared before the context so that its GL resources are freed first
+    canvas: Canvas<OpenGl>,
+    // Only kept alive for the canvas, which uses whichever context is current
+    _context: Context<PossiblyCurrent>,
+    state: State,
+    event_manager: EventManager,
+    renderer: DamageRenderer,
+    scale_factor: f32,
+}
+
+impl HeadlessWindow {
+    pub fn state(&mut self) -> &mut State {
+        &mut self.state
+    }
+
+    pub fn scale_factor(&self) -> f32 {
+        self.scale_factor
+    }
+
+    /// Handle all queued events, returning whether anything needs to be drawn again
+    pub fn flush_events(&mut self) -> bool {
+        self.event_manager.flush_events(&mut self.state)
+    }
+
+    /// Handle all queued events and draw what changed since the last call, the same way a baseview
+    /// window draws its frames
+    pub fn render(&mut self) {
+        self.event_manager.update(&mut self.state);
+
+        let damage = std::mem::take(&mut self.event_manager.damage);
+
+        let width = self.state.transform.get_width(self.state.root) * self.scale_factor;
+        let height = self.state.transform.get_height(self.state.root) * self.scale_factor;
+
+        self.canvas.set_size(width.round() as u32, height.round() as u32, self.scale_factor);
+
+        // Drawn even without damage, since the window contents are shown on the screen from the
+        // render targets every time
+        self.renderer.render(&mut self.canvas, &mut self.state, &mut self.event_manager, damage, self.scale_factor);
+
+        self.canvas.flush();
+    }
+
+    /// Read back the pixels drawn by the last call to `render`
+    pub fn screenshot(&mut self) -> Result<RgbaImage, HeadlessError> {
+        let screenshot = self.canvas.screenshot()?;
+
+        let pixels = screenshot
+            .pixels()
+            .flat_map(|pixel| vec![pixel.r, pixel.g, pixel.b, pixel.a])
+            .collect();
+
+        Ok(RgbaImage::from_raw(screenshot.width() as u32, screenshot.height() as u32, pixels)
+            .expect("Screenshot size doesn't match its pixels"))
+    }
+
+    /// Render the window and save it as an image, with the format taken from the file extension
+    pub fn save<P: AsRef<Path>>(&mut self, path: P) -> Result<(), HeadlessError> {
+        self.render();
+        self.screenshot()?.save(path)?;
+
+        Ok(())
+    }
+}
+
+impl<F: FnMut(WindowDescription, &mut State, Entity) -> WindowDescription> ApplicationBuilder<F> {
+    /// Build the window state without opening a platform window, rendering offscreen at the
+    /// given scale factor instead
+    pub fn open_headless(&mut self, scale_factor: f64) -> Result<HeadlessWindow, HeadlessError> {
+        let font_data = self.fonts.load()?;
+
+        let (mut state, window_open_options) = self.build_state();
+
+        let scale_factor = scale_factor as f32;
+        let width = (window_open_options.size.width as f32 * scale_factor).round() as u32;
+        let height = (window_open_options.size.height as f32 * scale_factor).round() as u32;
+
+        let context = create_context(width, height)?;
+        let context = unsafe { context.make_current() }.map_err(|(_, err)| {
+            HeadlessError::Context(CreationError::OsError(err.to_string()))
+        })?;
+
+        gl::load_with(|symbol| context.get_proc_address(symbol) as *const _);
+        let renderer = OpenGl::new(|symbol| context.get_proc_address(symbol) as *const _)?;
+        let mut canvas = Canvas::new(renderer)?;
+        canvas.set_size(width, height, scale_factor);
+
+        let (fonts, named_fonts) = font_data.register(&mut canvas)?;
+
+        state.fonts = fonts;
+
+        for (name, font_id) in named_fonts {
+            state.insert_event(Event::new(FontEvent::Registered(name, font_id)).target(Entity::null()));
+        }
+
+        Ok(HeadlessWindow {
+            canvas,
+            _context: context,
+            state,
+            event_manager: EventManager::new(),
+            renderer: DamageRenderer::new(false),
+            scale_factor,
+        })
+    }
+}
@@ -0,0 +1,456 @@
+use tuix::events::Event;
+use tuix::window::WindowEvent;
+use tuix::{Entity, Length, State, Visibility};
+
+/// The width of an entity's border in logical pixels, with percentages relative to the parent width
+pub fn border_width(state: &State, entity: Entity) -> f32 {
+    resolve_length(state, entity, state.style.border_width.get(entity).cloned().unwrap_or_default())
+}
+
+fn resolve_length(state: &State, entity: Entity, length: Length) -> f32 {
+    match length {
+        Length::Pixels(val) => val,
+        Length::Percentage(val) => {
+            let parent = state.hierarchy.get_parent(entity).unwrap_or(state.root);
+            state.transform.get_width(parent) * val
+        }
+        _ => 0.0,
+    }
+}
+
+/// Whether an entity can receive mouse input at all, regardless of the cursor position
+pub fn is_hoverable(state: &State, entity: Entity) -> bool {
+    state.transform.get_visibility(entity) != Visibility::Invisible
+        // Fully transparent entities are still laid out but shouldn't block the ones below them
+        && state.transform.get_opacity(entity) != 0.0
+        && state.transform.get_hoverability(entity)
+}
+
+// Whether a point lies within the unclipped bounds of an entity, without any rounding or transforms
+fn in_bounds(state: &State, entity: Entity, x: f32, y: f32) -> bool {
+    let posx = state.transform.get_posx(entity);
+    let posy = state.transform.get_posy(entity);
+
+    x >= posx
+        && x < posx + state.transform.get_width(entity)
+        && y >= posy
+        && y < posy + state.transform.get_height(entity)
+}
+
+// Rotate the point the opposite way around the center of the entity, which is how it's drawn
+fn untransform(state: &State, entity: Entity, x: f32, y: f32) -> (f32, f32) {
+    let rotate = state.style.rotate.get(entity).cloned().unwrap_or_default();
+
+    if rotate == 0.0 {
+        return (x, y);
+    }
+
+    let centerx = state.transform.get_posx(entity) + state.transform.get_width(entity) / 2.0;
+    let centery = state.transform.get_posy(entity) + state.transform.get_height(entity) / 2.0;
+
+    let (sin, cos) = (-rotate.to_radians()).sin_cos();
+    let (dx, dy) = (x - centerx, y - centery);
+
+    (centerx + dx * cos - dy * sin, centery + dx * sin + dy * cos)
+}
+
+// Whether a point within the bounds of an entity isn't in a corner cut off by its border radius
+fn in_rounded_corners(
+    state: &State,
+    entity: Entity,
+    bounds: (f32, f32, f32, f32),
+    border_width: f32,
+    x: f32,
+    y: f32,
+) -> bool {
+    let (posx, posy, width, height) = bounds;
+
+    // Femtovg limits each radius to half the shortest side
+    let max_radius = width.min(height) / 2.0;
+    let radius = |length: Option<&Length>| {
+        let radius = resolve_length(state, entity, length.cloned().unwrap_or_default());
+        if radius > 0.0 { (radius + border_width / 2.0).min(max_radius) } else { 0.0 }
+    };
+
+    let corners = [
+        (radius(state.style.border_radius_top_left.get(entity)), posx, posy),
+        (radius(state.style.border_radius_top_right.get(entity)), posx + width, posy),
+        (radius(state.style.border_radius_bottom_left.get(entity)), posx, posy + height),
+        (radius(state.style.border_radius_bottom_right.get(entity)), posx + width, posy + height),
+    ];
+
+    corners.iter().all(|&(radius, cornerx, cornery)| {
+        if radius <= 0.0 {
+            return true;
+        }
+
+        // The center of the arc is inset from the corner by the radius in both directions
+        let centerx = if cornerx == posx { posx + radius } else { cornerx - radius };
+        let centery = if cornery == posy { posy + radius } else { cornery - radius };
+
+        let outside_x = if cornerx == posx { x < centerx } else { x > centerx };
+        let outside_y = if cornery == posy { y < centery } else { y > centery };
+
+        !(outside_x && outside_y)
+            || (x - centerx) * (x - centerx) + (y - centery) * (y - centery) <= radius * radius
+    })
+}
+
+// Returns whether the pseudo-class changed
+fn set_over(state: &mut State, entity: Entity, over: bool) -> bool {
+    match state.style.pseudo_classes.get_mut(entity) {
+        Some(pseudo_classes) if pseudo_classes.get_over() != over => {
+            pseudo_classes.set_over(over);
+            true
+        }
+        _ => false,
+    }
+}
+
+// Styles can depend on the `over`, `hover` and `active` pseudo-classes, and may change the layout.
+// Only the entities whose pseudo-classes changed look different, so only they are drawn again,
+// along with anything the layout moves. Drawing the root would draw the whole window each time the
+// cursor moves onto the background, so the root is only drawn when nothing else changed.
+pub fn restyle(state: &mut State, changed: &[Entity]) {
+    let root = state.root;
+
+    if changed.iter().all(|entity| *entity == Entity::null()) {
+        return;
+    }
+
+    // A restyle from the root damages the whole window
+    let mut origin = root;
+
+    for &entity in changed {
+        if entity != Entity::null() && entity != root {
+            state.insert_event(Event::new(WindowEvent::Redraw).target(entity));
+            origin = entity;
+        }
+    }
+
+    state.insert_event(Event::new(WindowEvent::Restyle).target(Entity::null()).origin(origin));
+    state.insert_event(Event::new(WindowEvent::Relayout).target(Entity::null()).origin(origin));
+}
+
+/// Finds the entities under the cursor.
+///
+/// The entities sorted by z-order are cached, and only sorted again after `invalidate`. Changes to
+/// the hierarchy and to z-orders are followed by a relayout, which invalidates the cache.
+#[derive(Debug, Default)]
+pub struct HitTest {
+    /// Exclude the corners cut off by `border-radius`
+    pub border_radius: bool,
+    /// Test the cursor against entities the way they're drawn with `rotate`
+    pub transforms: bool,
+    // From back to front, with entities of the same z-order in hierarchy order
+    sorted: Vec<Entity>,
+    // Whether `sorted` is up to date with the hierarchy and z-orders
+    valid: bool,
+}
+
+impl HitTest {
+    pub fn new(border_radius: bool, transforms: bool) -> Self {
+        HitTest {
+            border_radius,
+            transforms,
+            ..HitTest::default()
+        }
+    }
+
+    /// Update the `over` pseudo-class of every entity and return the frontmost hoverable entity
+    /// under the point, or the root if there is none
+    pub fn update_over(&mut self, state: &mut State, x: f32, y: f32) -> Entity {
+        self.update_cache(state);
+
+        let mut hovered = state.root;
+        let mut changed = Vec::new();
+
+        for &entity in self.sorted.iter() {
+            let over = is_hoverable(state, entity) && self.contains(state, entity, x, y);
+
+            if over {
+                hovered = entity;
+            }
+
+            if set_over(state, entity, over) {
+                changed.push(entity);
+            }
+        }
+
+        restyle(state, &changed);
+
+        hovered
+    }
+
+    /// Clear the `over` pseudo-class of every entity, e.g. when the cursor leaves the window
+    pub fn clear_over(&mut self, state: &mut State) {
+        self.update_cache(state);
+
+        let mut changed = Vec::new();
+
+        for &entity in self.sorted.iter() {
+            if set_over(state, entity, false) {
+                changed.push(entity);
+            }
+        }
+
+        restyle(state, &changed);
+    }
+
+    /// Whether a point lies within an entity, including its border, and within the clip regions of
+    /// all its ancestors
+    pub fn contains(&self, state: &State, entity: Entity, x: f32, y: f32) -> bool {
+        // Clip regions aren't rotated with the entity, so only its own bounds use the rotated point
+        let (localx, localy) = if self.transforms { untransform(state, entity, x, y) } else { (x, y) };
+
+        let border_width = border_width(state, entity);
+
+        let posx = state.transform.get_posx(entity) - border_width / 2.0;
+        let posy = state.transform.get_posy(entity) - border_width / 2.0;
+        let width = state.transform.get_width(entity) + border_width;
+        let height = state.transform.get_height(entity) + border_width;
+
+        if localx < posx || localx >= posx + width || localy < posy || localy >= posy + height {
+            return false;
+        }
+
+        let bounds = (posx, posy, width, height);
+
+        if self.border_radius && !in_rounded_corners(state, entity, bounds, border_width, localx, localy) {
+            return false;
+        }
+
+        // Widgets inside nested scroll containers are clipped by every container, not just the nearest
+        let mut clip_widget = state.transform.get_clip_widget(entity);
+
+        while clip_widget != Entity::null() {
+            if !in_bounds(state, clip_widget, x, y) {
+                return false;
+            }
+
+            let parent_clip = state.transform.get_clip_widget(clip_widget);
+
+            if clip_widget == state.root || parent_clip == clip_widget {
+                break;
+            }
+
+            clip_widget = parent_clip;
+        }
+
+        true
+    }
+
+    /// Sort the entities again before the next hit test, after the hierarchy or a z-order changed
+    pub fn invalidate(&mut self) {
+        self.valid = false;
+    }
+
+    fn update_cache(&mut self, state: &State) {
+        if self.valid {
+            return;
+        }
+
+        self.sorted = state.hierarchy.into_iter().collect();
+        // The sort is stable, so entities with the same z-order stay in hierarchy order
+        self.sorted.sort_by_key(|entity| state.transform.get_z_order(*entity));
+        self.valid = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tuix::window::WindowWidget;
+
+    // A 200x200 window. Layout isn't run, so the bounds set here are the ones tested.
+    fn test_state() -> State {
+        let mut state = State::new();
+
+        WindowWidget::new().build_window(&mut state);
+        state.hierarchy.add(state.root, None);
+
+        let root = state.root;
+        set_bounds(&mut state, root, (0.0, 0.0, 200.0, 200.0));
+        state.transform.set_opacity(root, 1.0);
+
+        state
+    }
+
+    fn set_bounds(state: &mut State, entity: Entity, (posx, posy, width, height): (f32, f32, f32, f32)) {
+        state.transform.set_posx(entity, posx);
+        state.transform.set_posy(entity, posy);
+        state.transform.set_width(entity, width);
+        state.transform.set_height(entity, height);
+    }
+
+    // A visible, hoverable entity clipped by `clip`
+    fn add_entity(state: &mut State, parent: Entity, clip: Entity, bounds: (f32, f32, f32, f32)) -> Entity {
+        let entity = state.add(parent);
+
+        set_bounds(state, entity, bounds);
+        state.transform.set_clip_widget(entity, clip);
+        state.transform.set_visibility(entity, Visibility::Visible);
+        state.transform.set_opacity(entity, 1.0);
+        state.transform.set_hoverability(entity, true);
+
+        entity
+    }
+
+    #[test]
+    fn contains_points_within_bounds() {
+        let mut state = test_state();
+        let root = state.root;
+        let entity = add_entity(&mut state, root, root, (10.0, 10.0, 50.0, 50.0));
+
+        let hit_test = HitTest::new(false, false);
+
+        assert!(hit_test.contains(&state, entity, 10.0, 10.0));
+        assert!(hit_test.contains(&state, entity, 59.0, 59.0));
+        assert!(!hit_test.contains(&state, entity, 60.0, 30.0));
+        assert!(!hit_test.contains(&state, entity, 5.0, 30.0));
+    }
+
+    #[test]
+    fn percentage_borders_are_relative_to_the_parent_width() {
+        let mut state = test_state();
+        let root = state.root;
+        let parent = add_entity(&mut state, root, root, (0.0, 0.0, 100.0, 100.0));
+        let entity = add_entity(&mut state, parent, root, (20.0, 20.0, 40.0, 40.0));
+        // 10 pixels wide, half of which lies outside of the bounds
+        state.style.border_width.insert(entity, Length::Percentage(0.1));
+
+        let hit_test = HitTest::new(false, false);
+
+        assert!(hit_test.contains(&state, entity, 16.0, 30.0));
+        assert!(hit_test.contains(&state, entity, 64.0, 30.0));
+        // Would be within the border if it were relative to the window width
+        assert!(!hit_test.contains(&state, entity, 14.0, 30.0));
+        assert!(!hit_test.contains(&state, entity, 30.0, 66.0));
+    }
+
+    #[test]
+    fn clipped_by_every_clip_widget() {
+        let mut state = test_state();
+        let root = state.root;
+        let outer = add_entity(&mut state, root, root, (0.0, 0.0, 150.0, 150.0));
+        let inner = add_entity(&mut state, outer, outer, (0.0, 0.0, 100.0, 200.0));
+        let entity = add_entity(&mut state, inner, inner, (50.0, 50.0, 100.0, 150.0));
+
+        let hit_test = HitTest::new(false, false);
+
+        assert!(hit_test.contains(&state, entity, 60.0, 60.0));
+        // Outside of the nearest clip widget
+        assert!(!hit_test.contains(&state, entity, 120.0, 60.0));
+        // Within the nearest clip widget but outside of the one clipping it
+        assert!(!hit_test.contains(&state, entity, 60.0, 160.0));
+    }
+
+    #[test]
+    fn rounded_corners_are_excluded() {
+        let mut state = test_state();
+        let root = state.root;
+        let entity = add_entity(&mut state, root, root, (0.0, 0.0, 100.0, 100.0));
+        state.style.border_radius_top_left.insert(entity, Length::Pixels(20.0));
+
+        assert!(!HitTest::new(true, false).contains(&state, entity, 2.0, 2.0));
+        assert!(HitTest::new(false, false).contains(&state, entity, 2.0, 2.0));
+        assert!(HitTest::new(true, false).contains(&state, entity, 10.0, 10.0));
+        assert!(HitTest::new(true, false).contains(&state, entity, 98.0, 2.0));
+    }
+
+    #[test]
+    fn rotated_entities_are_tested_as_drawn() {
+        let mut state = test_state();
+        let root = state.root;
+        let entity = add_entity(&mut state, root, root, (50.0, 50.0, 100.0, 100.0));
+        state.style.rotate.insert(entity, 45.0);
+
+        // The corner of the unrotated square is outside of the rotated one
+        assert!(!HitTest::new(false, true).contains(&state, entity, 55.0, 55.0));
+        assert!(HitTest::new(false, false).contains(&state, entity, 55.0, 55.0));
+        // The rotated corners reach past the unrotated bounds
+        assert!(HitTest::new(false, true).contains(&state, entity, 100.0, 45.0));
+    }
+
+    #[test]
+    fn hovers_the_frontmost_entity() {
+        let mut state = test_state();
+        let root = state.root;
+        let back = add_entity(&mut state, root, root, (0.0, 0.0, 100.0, 100.0));
+        let front = add_entity(&mut state, root, root, (50.0, 50.0, 100.0, 100.0));
+
+        let mut hit_test = HitTest::new(false, false);
+
+        assert_eq!(hit_test.update_over(&mut state, 25.0, 25.0), back);
+        assert_eq!(hit_test.update_over(&mut state, 75.0, 75.0), front);
+        assert_eq!(hit_test.update_over(&mut state, 190.0, 10.0), root);
+
+        // A higher z-order is in front regardless of the hierarchy order, once the relayout which
+        // follows the change invalidates the cache
+        state.transform.set_z_order(back, 1);
+        hit_test.invalidate();
+        assert_eq!(hit_test.update_over(&mut state, 75.0, 75.0), back);
+    }
+
+    #[test]
+    fn skips_entities_which_cannot_be_hovered() {
+        let mut state = test_state();
+        let root = state.root;
+        let back = add_entity(&mut state, root, root, (0.0, 0.0, 100.0, 100.0));
+        let front = add_entity(&mut state, root, root, (0.0, 0.0, 100.0, 100.0));
+
+        let mut hit_test = HitTest::new(false, false);
+
+        assert_eq!(hit_test.update_over(&mut state, 50.0, 50.0), front);
+
+        state.transform.set_visibility(front, Visibility::Invisible);
+        assert_eq!(hit_test.update_over(&mut state, 50.0, 50.0), back);
+
+        state.transform.set_visibility(front, Visibility::Visible);
+        state.transform.set_opacity(front, 0.0);
+        assert_eq!(hit_test.update_over(&mut state, 50.0, 50.0), back);
+
+        state.transform.set_opacity(front, 1.0);
+        state.transform.set_hoverability(front, false);
+        assert_eq!(hit_test.update_over(&mut state, 50.0, 50.0), back);
+
+        state.transform.set_hoverability(back, false);
+        assert_eq!(hit_test.update_over(&mut state, 50.0, 50.0), root);
+    }
+
+    #[test]
+    fn only_entities_whose_over_changed_are_redrawn() {
+        let mut state = test_state();
+        let root = state.root;
+        let first = add_entity(&mut state, root, root, (0.0, 0.0, 50.0, 50.0));
+        let second = add_entity(&mut state, root, root, (100.0, 100.0, 50.0, 50.0));
+
+        let mut hit_test = HitTest::new(false, false);
+
+        hit_test.update_over(&mut state, 25.0, 25.0);
+        state.event_queue.clear();
+
+        hit_test.update_over(&mut state, 125.0, 125.0);
+
+        let mut redrawn = Vec::new();
+        let mut restyle_origin = None;
+
+        for event in state.event_queue.iter_mut() {
+            match event.message.downcast::<WindowEvent>() {
+                Some(WindowEvent::Redraw) => redrawn.push(event.target),
+                Some(WindowEvent::Restyle) => restyle_origin = Some(event.origin),
+                _ => {}
+            }
+        }
+
+        assert_eq!(redrawn, vec![first, second]);
+        // A restyle from the root would damage the whole window
+        assert!(matches!(restyle_origin, Some(origin) if origin != root && origin != Entity::null()));
+
+        // Nothing changes while the cursor stays over the same entities
+        state.event_queue.clear();
+        hit_test.update_over(&mut state, 130.0, 130.0);
+        assert!(state.event_queue.is_empty());
+    }
+}
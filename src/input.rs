@@ -0,0 +1,540 @@
+use std::time::{Duration, Instant};
+
+use tuix::window::WindowEvent;
+
+use tuix::{CursorIcon, Entity, State};
+
+use tuix::state::mouse::{MouseButton, MouseButtonData, MouseButtonState};
+
+use tuix::events::{Event, Message, Propagation};
+
+use keyboard_types::{Code, Key, KeyState, KeyboardEvent, Location, Modifiers};
+
+use crate::clipboard::ClipboardEvent;
+use crate::cursor::CursorStyle;
+use crate::event_manager::EventManager;
+use crate::hit_test::{self, HitTest};
+use crate::keyboard;
+
+/// Settings which control how baseview input is translated into tuix events
+#[derive(Debug, Clone, Copy)]
+pub struct InputSettings {
+    /// The number of pixels in one line of scrolling. Tuix widgets expect `MouseScroll`
+    /// in lines, so pixel based scroll deltas are divided by this factor.
+    pub scroll_line_pixels: f32,
+    /// Exclude the corners cut off by `border-radius` when finding the hovered entity
+    pub hit_test_border_radius: bool,
+    /// Account for `rotate` when finding the hovered entity
+    pub hit_test_transforms: bool,
+    /// The longest time between two presses of a button for them to count as a double click
+    pub double_click_time: Duration,
+    /// How far in logical pixels the cursor can move between the presses of a double click
+    pub double_click_distance: f32,
+    /// Paste the primary selection into the hovered entity when the middle button is pressed.
+    /// Enabled by default on Linux, where the primary selection is expected.
+    pub middle_click_paste: bool,
+}
+
+impl Default for InputSettings {
+    fn default() -> Self {
+        InputSettings {
+            scroll_line_pixels: 20.0,
+            hit_test_border_radius: true,
+            hit_test_transforms: true,
+            double_click_time: Duration::from_millis(500),
+            double_click_distance: 4.0,
+            middle_click_paste: cfg!(target_os = "linux"),
+        }
+    }
+}
+
+/// Sent to every entity when the cursor enters or leaves the window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CursorEvent {
+    Entered,
+    Left,
+}
+
+/// Sent to the pressed entity after every `MouseDown`, with the number of presses in quick
+/// succession. A count of 2 also sends `WindowEvent::MouseDoubleClick`, higher counts can be used
+/// for e.g. selecting a line of text on a triple click.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClickEvent {
+    Clicked(MouseButton, u32),
+}
+
+/// Sent with the whole keyboard event to the focused entity only, right before the `KeyDown` or
+/// `KeyUp` for the same key, when those can't describe the key by themselves.
+///
+/// Tuix's `KeyDown(Option<VirtualKeyCode>)` has no room for the key location, for whether the
+/// press is a key repeat or for keys without a virtual key code, so this is sent for repeated
+/// presses, keys outside of the main block of the keyboard and keys without a key code. Widgets
+/// which e.g. ignore held keys can skip the `KeyDown` which follows a repeated press. Repeated
+/// presses still send `KeyDown` and `CharInput`, so holding a key types it repeatedly.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyEvent {
+    Pressed(KeyboardEvent),
+    Released(KeyboardEvent),
+}
+
+// The last button press, to count presses which follow it as a double or triple click
+struct LastClick {
+    button: MouseButton,
+    time: Instant,
+    position: (f32, f32),
+    target: Entity,
+    count: u32,
+}
+
+/// Translates baseview mouse and keyboard events into tuix events
+pub(crate) struct InputHandler {
+    pub settings: InputSettings,
+    hit_test: HitTest,
+    last_click: Option<LastClick>,
+    cursor_style: CursorStyle,
+    // The cursor last set from the `cursor` property of the hovered entity
+    styled_cursor: Option<CursorIcon>,
+}
+
+impl InputHandler {
+    pub fn new(settings: InputSettings) -> Self {
+        InputHandler {
+            settings,
+            hit_test: HitTest::new(settings.hit_test_border_radius, settings.hit_test_transforms),
+            last_click: None,
+            cursor_style: CursorStyle::default(),
+            styled_cursor: None,
+        }
+    }
+
+    /// Translate a mouse or keyboard event and handle the events it causes straight away, so that
+    /// the host can be told whether anything used it. Unused key presses can then be used for the
+    /// host's own shortcuts. Window events aren't input and are ignored.
+    pub fn handle_input(
+        &mut self,
+        event_manager: &mut EventManager,
+        state: &mut State,
+        event: baseview::Event,
+    ) -> baseview::EventStatus {
+        // Handle anything still queued first, so that it doesn't change what this input does
+        event_manager.flush_events(state);
+
+        if std::mem::take(&mut event_manager.relayout_handled) {
+            self.hit_test.invalidate();
+        }
+
+        // Handlers may have queued more events while flushing, which are not part of this input
+        let first_input = state.event_queue.len();
+
+        match event {
+            baseview::Event::Mouse(e) => self.on_mouse_event(state, e),
+            baseview::Event::Keyboard(e) => self.on_keyboard_event(state, e),
+            baseview::Event::Window(_) => return baseview::EventStatus::Ignored,
+        }
+
+        event_manager.flush_input(state, first_input);
+
+        // Handlers may have changed pseudo-classes which cursor rules depend on, e.g. `:active`
+        self.update_cursor(state);
+
+        if event_manager.consumed {
+            baseview::EventStatus::Captured
+        } else {
+            baseview::EventStatus::Ignored
+        }
+    }
+
+    pub fn on_mouse_event(&mut self, state: &mut State, e: baseview::MouseEvent) {
+        match e {
+            // Styles are only applied again when the hover or `over` pseudo-classes change
+            baseview::MouseEvent::CursorMoved { position, .. } => {
+                // Baseview reports the cursor position in logical pixels, the same
+                // coordinate space as the layout
+                let cursorx = (position.x) as f32;
+                let cursory = (position.y) as f32;
+
+                state.mouse.cursorx = cursorx as f32;
+                state.mouse.cursory = cursory as f32;
+
+                let hovered_widget = self.hit_test.update_over(state, cursorx, cursory);
+
+                if hovered_widget != state.hovered {
+                    set_hovered(state, hovered_widget);
+                }
+
+                if state.captured != Entity::null() {
+                    state.insert_event(
+                        Event::new(WindowEvent::MouseMove(cursorx, cursory))
+                            .target(state.captured)
+                            .propagate(Propagation::Direct),
+                    );
+                } else if state.hovered != Entity::new(0, 0) {
+                    state.insert_event(
+                        Event::new(WindowEvent::MouseMove(cursorx, cursory))
+                            .target(state.hovered),
+                    );
+                }
+            }
+
+            baseview::MouseEvent::ButtonPressed { button, .. } => {
+                let b = translate_button(button);
+
+                let cursor = (state.mouse.cursorx, state.mouse.cursory);
+                let hovered = state.hovered;
+
+                if let Some(data) = button_data(state, b) {
+                    data.state = MouseButtonState::Pressed;
+                    data.pos_down = cursor;
+                    data.pressed = hovered;
+                }
+
+                if state.hovered != Entity::null()
+                    && state.active != state.hovered
+                {
+                    let previous = state.active;
+                    state.active = state.hovered;
+                    hit_test::restyle(state, &[previous, state.active]);
+                }
+
+                let target = if state.captured != Entity::null() { state.captured } else { state.hovered };
+                let count = self.count_click(b, cursor, target, Instant::now());
+
+                send_mouse_event(state, WindowEvent::MouseDown(b));
+
+                if count == 2 {
+                    send_mouse_event(state, WindowEvent::MouseDoubleClick(b));
+                }
+
+                send_mouse_event(state, ClickEvent::Clicked(b, count));
+
+                // Answered with `Paste` after the `MouseDown`, so widgets can first move their caret
+                // to the cursor
+                if b == MouseButton::Middle && self.settings.middle_click_paste && state.hovered != Entity::null() {
+                    state.insert_event(
+                        Event::new(ClipboardEvent::RequestPrimary)
+                            .target(state.hovered)
+                            .origin(state.hovered)
+                            .propagate(Propagation::Direct),
+                    );
+                }
+            }
+
+            baseview::MouseEvent::ButtonReleased { button, .. } => {
+                let b = translate_button(button);
+
+                let cursor = (state.mouse.cursorx, state.mouse.cursory);
+                let hovered = state.hovered;
+
+                if let Some(data) = button_data(state, b) {
+                    data.state = MouseButtonState::Released;
+                    data.pos_up = cursor;
+                    data.released = hovered;
+                }
+
+                let previous = state.active;
+                state.active = Entity::null();
+                hit_test::restyle(state, &[previous]);
+
+                send_mouse_event(state, WindowEvent::MouseUp(b));
+            }
+
+            baseview::MouseEvent::WheelScrolled { delta, .. } => {
+                let (x, y) = match delta {
+                    baseview::ScrollDelta::Lines{x, y} => (x, y),
+                    baseview::ScrollDelta::Pixels{x, y} => {
+                        let line_pixels = self.settings.scroll_line_pixels;
+                        (x / line_pixels, y / line_pixels)
+                    }
+                };
+
+                send_mouse_event(state, WindowEvent::MouseScroll(x, y));
+            }
+
+            baseview::MouseEvent::CursorEntered => {
+                state.insert_event(Event::new(CursorEvent::Entered).target(Entity::null()));
+            }
+
+            // Nothing is hovered while the cursor is outside of the window, but a captured entity
+            // keeps receiving mouse events so that drags can continue outside of it
+            baseview::MouseEvent::CursorLeft => {
+                self.hit_test.clear_over(state);
+                set_hovered(state, Entity::null());
+
+                state.insert_event(Event::new(CursorEvent::Left).target(Entity::null()));
+            }
+
+            // Tuix has no events for dragging files into the window
+            _ => {}
+        }
+    }
+
+    // Show the cursor from the `cursor` property of the hovered entity, going back to the default
+    // cursor when leaving an entity which set one. Cursors set by widgets with `SetCursor` are
+    // left alone otherwise. Called after the events from each input have been handled, so that
+    // rules using pseudo-classes set by the handlers apply.
+    pub fn update_cursor(&mut self, state: &mut State) {
+        // Themes can be inserted into the state at any time, e.g. by the app closure
+        if self.cursor_style.is_outdated(state) {
+            self.cursor_style = CursorStyle::from_state(state);
+        }
+
+        if self.cursor_style.is_empty() {
+            return;
+        }
+
+        let cursor = self.cursor_style.cursor(state, state.hovered);
+
+        if cursor != self.styled_cursor {
+            let icon = cursor.unwrap_or(CursorIcon::Arrow);
+            state.insert_event(Event::new(WindowEvent::SetCursor(icon)).target(state.root));
+            self.styled_cursor = cursor;
+        }
+    }
+
+    // Count presses of the same button on the same entity which follow each other closely enough
+    fn count_click(&mut self, button: MouseButton, position: (f32, f32), target: Entity, now: Instant) -> u32 {
+        let count = match &self.last_click {
+            Some(last)
+                if last.button == button
+                    && last.target == target
+                    && now.duration_since(last.time) <= self.settings.double_click_time
+                    && (position.0 - last.position.0).hypot(position.1 - last.position.1)
+                        <= self.settings.double_click_distance =>
+            {
+                last.count + 1
+            }
+            _ => 1,
+        };
+
+        self.last_click = Some(LastClick {
+            button,
+            time: now,
+            position,
+            target,
+            count,
+        });
+
+        count
+    }
+
+    pub fn on_keyboard_event(&mut self, state: &mut State, e: KeyboardEvent) {
+        let pressed = e.state == KeyState::Down;
+
+        // Keep the modifier state in sync, including the modifier key itself
+        // which may or may not be reflected in the event modifiers
+        state.modifiers.shift = e.modifiers.contains(Modifiers::SHIFT);
+        state.modifiers.ctrl = e.modifiers.contains(Modifiers::CONTROL);
+        state.modifiers.alt = e.modifiers.contains(Modifiers::ALT);
+        state.modifiers.logo = e.modifiers.contains(Modifiers::META);
+
+        match e.code {
+            Code::ShiftLeft | Code::ShiftRight => state.modifiers.shift = pressed,
+            Code::ControlLeft | Code::ControlRight => state.modifiers.ctrl = pressed,
+            Code::AltLeft | Code::AltRight => state.modifiers.alt = pressed,
+            Code::MetaLeft | Code::MetaRight => state.modifiers.logo = pressed,
+            _ => {}
+        }
+
+        if e.is_composing {
+            return;
+        }
+
+        let keycode = keyboard::virtual_keycode(&e);
+
+        // Only sent when KeyDown and KeyUp leave something out, so that ordinary key presses go
+        // through the hierarchy once
+        if e.repeat || e.location != Location::Standard || keycode.is_none() {
+            let key_event = match e.state {
+                KeyState::Down => KeyEvent::Pressed(e.clone()),
+                KeyState::Up => KeyEvent::Released(e.clone()),
+            };
+
+            state.insert_event(
+                Event::new(key_event)
+                    .target(state.focused)
+                    .propagate(Propagation::Direct),
+            );
+        }
+
+        match e.state {
+            KeyState::Down => {
+                state.insert_event(
+                    Event::new(WindowEvent::KeyDown(keycode))
+                        .target(state.focused)
+                        .propagate(Propagation::DownUp),
+                );
+
+                // Ctrl on Linux and Windows, Cmd on macOS
+                let shortcut = if cfg!(target_os = "macos") { state.modifiers.logo } else { state.modifiers.ctrl };
+
+                if shortcut && !state.modifiers.shift && !state.modifiers.alt {
+                    let clipboard_event = match e.code {
+                        Code::KeyC => Some(ClipboardEvent::Copy),
+                        Code::KeyX => Some(ClipboardEvent::Cut),
+                        // Answered with `Paste` sent to the focused entity
+                        Code::KeyV => Some(ClipboardEvent::Request),
+                        _ => None,
+                    };
+
+                    if let Some(clipboard_event) = clipboard_event {
+                        state.insert_event(
+                            Event::new(clipboard_event)
+                                .target(state.focused)
+                                .origin(state.focused)
+                                .propagate(Propagation::Direct),
+                        );
+                    }
+                }
+
+                // Shortcuts should not produce text input
+                if !state.modifiers.ctrl && !state.modifiers.logo {
+                    if let Key::Character(text) = &e.key {
+                        for chr in text.chars().filter(|chr| !chr.is_control()) {
+                            state.insert_event(
+                                Event::new(WindowEvent::CharInput(chr))
+                                    .target(state.focused)
+                                    .propagate(Propagation::Down),
+                            );
+                        }
+                    }
+                }
+            }
+
+            KeyState::Up => {
+                state.insert_event(
+                    Event::new(WindowEvent::KeyUp(keycode))
+                        .target(state.focused)
+                        .propagate(Propagation::DownUp),
+                );
+            }
+        }
+    }
+}
+
+// Move the hover from the previously hovered entity to a new one, which is null while the cursor
+// is outside of the window
+fn set_hovered(state: &mut State, hovered: Entity) {
+    if hovered == state.hovered {
+        return;
+    }
+
+    // The null entity has no pseudo-classes, and an event aimed at it would be sent to every entity
+    if hovered != Entity::null() {
+        if let Some(pseudo_classes) = state.style.pseudo_classes.get_mut(hovered) {
+            pseudo_classes.set_hover(true);
+        }
+
+        state.insert_event(Event::new(WindowEvent::MouseOver).target(hovered));
+    }
+
+    if state.hovered != Entity::null() {
+        if let Some(pseudo_classes) = state.style.pseudo_classes.get_mut(state.hovered) {
+            pseudo_classes.set_hover(false);
+        }
+
+        state.insert_event(Event::new(WindowEvent::MouseOut).target(state.hovered));
+    }
+
+    let previous = (state.hovered, state.active);
+
+    state.hovered = hovered;
+    state.active = Entity::null();
+
+    // Only the entities which gained or lost `hover` or `active` are drawn again
+    hit_test::restyle(state, &[previous.0, hovered, previous.1]);
+}
+
+// Mouse events go to the entity which captured the mouse, or otherwise the hovered entity. They're
+// dropped when neither exists, since an event targeting the null entity is sent to every entity.
+fn send_mouse_event<M: Message>(state: &mut State, message: M) {
+    if state.captured != Entity::null() {
+        state.insert_event(Event::new(message).target(state.captured).propagate(Propagation::Direct));
+    } else if state.hovered != Entity::null() {
+        state.insert_event(Event::new(message).target(state.hovered));
+    }
+}
+
+// Back and forward have no tuix equivalent, so they use the button numbers X11 reports for them
+fn translate_button(button: baseview::MouseButton) -> MouseButton {
+    match button {
+        baseview::MouseButton::Left => MouseButton::Left,
+        baseview::MouseButton::Right => MouseButton::Right,
+        baseview::MouseButton::Middle => MouseButton::Middle,
+        baseview::MouseButton::Back => MouseButton::Other(8),
+        baseview::MouseButton::Forward => MouseButton::Other(9),
+        baseview::MouseButton::Other(id) => MouseButton::Other(id as u16),
+    }
+}
+
+// Tuix only keeps track of the left, right and middle buttons
+fn button_data(state: &mut State, button: MouseButton) -> Option<&mut MouseButtonData> {
+    match button {
+        MouseButton::Left => Some(&mut state.mouse.left),
+        MouseButton::Right => Some(&mut state.mouse.right),
+        MouseButton::Middle => Some(&mut state.mouse.middle),
+        MouseButton::Other(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input_handler() -> InputHandler {
+        InputHandler::new(InputSettings::default())
+    }
+
+    // Presses of the left button at the given times in milliseconds
+    fn count_clicks(input: &mut InputHandler, target: Entity, times: &[u64]) -> Vec<u32> {
+        let start = Instant::now();
+
+        times
+            .iter()
+            .map(|time| input.count_click(MouseButton::Left, (10.0, 10.0), target, start + Duration::from_millis(*time)))
+            .collect()
+    }
+
+    #[test]
+    fn counts_double_and_triple_clicks() {
+        let mut input = input_handler();
+        let target = Entity::new(1, 0);
+
+        assert_eq!(count_clicks(&mut input, target, &[0, 200, 400, 600]), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn count_resets_after_the_double_click_time() {
+        let mut input = input_handler();
+        let target = Entity::new(1, 0);
+
+        // The time is measured from the previous press, not the first
+        assert_eq!(count_clicks(&mut input, target, &[0, 500, 1000, 1501, 1600]), vec![1, 2, 3, 1, 2]);
+    }
+
+    #[test]
+    fn count_resets_when_the_cursor_moves_too_far() {
+        let mut input = input_handler();
+        let target = Entity::new(1, 0);
+        let now = Instant::now();
+
+        assert_eq!(input.count_click(MouseButton::Left, (10.0, 10.0), target, now), 1);
+        // Exactly the default distance of 4 pixels away still counts
+        assert_eq!(input.count_click(MouseButton::Left, (10.0, 14.0), target, now), 2);
+        assert_eq!(input.count_click(MouseButton::Left, (13.0, 18.0), target, now), 1);
+        assert_eq!(input.count_click(MouseButton::Left, (13.0, 22.1), target, now), 1);
+    }
+
+    #[test]
+    fn count_resets_for_another_button_or_target() {
+        let mut input = input_handler();
+        let first = Entity::new(1, 0);
+        let second = Entity::new(2, 0);
+        let now = Instant::now();
+
+        assert_eq!(input.count_click(MouseButton::Left, (10.0, 10.0), first, now), 1);
+        assert_eq!(input.count_click(MouseButton::Right, (10.0, 10.0), first, now), 1);
+        assert_eq!(input.count_click(MouseButton::Right, (10.0, 10.0), first, now), 2);
+        assert_eq!(input.count_click(MouseButton::Right, (10.0, 10.0), second, now), 1);
+        assert_eq!(input.count_click(MouseButton::Left, (10.0, 10.0), second, now), 1);
+    }
+}
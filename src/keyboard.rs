@@ -0,0 +1,304 @@
+use keyboard_types::{Code, Key, KeyboardEvent};
+
+use tuix::VirtualKeyCode;
+
+/// The tuix key code for a baseview keyboard event.
+///
+/// Letters and digits follow the keyboard layout like the character they type, so shortcuts
+/// such as Ctrl+Z stay on the key labelled Z. Every other key is identified by its position.
+pub(crate) fn virtual_keycode(e: &KeyboardEvent) -> Option<VirtualKeyCode> {
+    if let Key::Character(text) = &e.key {
+        let mut chars = text.chars();
+
+        if let (Some(chr), None) = (chars.next(), chars.next()) {
+            if let Some(keycode) = character_keycode(chr) {
+                // The numpad digits have keycodes of their own
+                if !is_numpad(e.code) {
+                    return Some(keycode);
+                }
+            }
+        }
+    }
+
+    code_keycode(e.code)
+}
+
+fn is_numpad(code: Code) -> bool {
+    matches!(
+        code,
+        Code::Numpad0
+            | Code::Numpad1
+            | Code::Numpad2
+            | Code::Numpad3
+            | Code::Numpad4
+            | Code::Numpad5
+            | Code::Numpad6
+            | Code::Numpad7
+            | Code::Numpad8
+            | Code::Numpad9
+            | Code::NumpadDecimal
+            | Code::NumpadComma
+    )
+}
+
+fn character_keycode(chr: char) -> Option<VirtualKeyCode> {
+    let keycode = match chr.to_ascii_lowercase() {
+        'a' => VirtualKeyCode::A,
+        'b' => VirtualKeyCode::B,
+        'c' => VirtualKeyCode::C,
+        'd' => VirtualKeyCode::D,
+        'e' => VirtualKeyCode::E,
+        'f' => VirtualKeyCode::F,
+        'g' => VirtualKeyCode::G,
+        'h' => VirtualKeyCode::H,
+        'i' => VirtualKeyCode::I,
+        'j' => VirtualKeyCode::J,
+        'k' => VirtualKeyCode::K,
+        'l' => VirtualKeyCode::L,
+        'm' => VirtualKeyCode::M,
+        'n' => VirtualKeyCode::N,
+        'o' => VirtualKeyCode::O,
+        'p' => VirtualKeyCode::P,
+        'q' => VirtualKeyCode::Q,
+        'r' => VirtualKeyCode::R,
+        's' => VirtualKeyCode::S,
+        't' => VirtualKeyCode::T,
+        'u' => VirtualKeyCode::U,
+        'v' => VirtualKeyCode::V,
+        'w' => VirtualKeyCode::W,
+        'x' => VirtualKeyCode::X,
+        'y' => VirtualKeyCode::Y,
+        'z' => VirtualKeyCode::Z,
+        '0' => VirtualKeyCode::Key0,
+        '1' => VirtualKeyCode::Key1,
+        '2' => VirtualKeyCode::Key2,
+        '3' => VirtualKeyCode::Key3,
+        '4' => VirtualKeyCode::Key4,
+        '5' => VirtualKeyCode::Key5,
+        '6' => VirtualKeyCode::Key6,
+        '7' => VirtualKeyCode::Key7,
+        '8' => VirtualKeyCode::Key8,
+        '9' => VirtualKeyCode::Key9,
+        _ => return None,
+    };
+
+    Some(keycode)
+}
+
+fn code_keycode(code: Code) -> Option<VirtualKeyCode> {
+    let keycode = match code {
+        Code::Digit0 => VirtualKeyCode::Key0,
+        Code::Digit1 => VirtualKeyCode::Key1,
+        Code::Digit2 => VirtualKeyCode::Key2,
+        Code::Digit3 => VirtualKeyCode::Key3,
+        Code::Digit4 => VirtualKeyCode::Key4,
+        Code::Digit5 => VirtualKeyCode::Key5,
+        Code::Digit6 => VirtualKeyCode::Key6,
+        Code::Digit7 => VirtualKeyCode::Key7,
+        Code::Digit8 => VirtualKeyCode::Key8,
+        Code::Digit9 => VirtualKeyCode::Key9,
+
+        Code::KeyA => VirtualKeyCode::A,
+        Code::KeyB => VirtualKeyCode::B,
+        Code::KeyC => VirtualKeyCode::C,
+        Code::KeyD => VirtualKeyCode::D,
+        Code::KeyE => VirtualKeyCode::E,
+        Code::KeyF => VirtualKeyCode::F,
+        Code::KeyG => VirtualKeyCode::G,
+        Code::KeyH => VirtualKeyCode::H,
+        Code::KeyI => VirtualKeyCode::I,
+        Code::KeyJ => VirtualKeyCode::J,
+        Code::KeyK => VirtualKeyCode::K,
+        Code::KeyL => VirtualKeyCode::L,
+        Code::KeyM => VirtualKeyCode::M,
+        Code::KeyN => VirtualKeyCode::N,
+        Code::KeyO => VirtualKeyCode::O,
+        Code::KeyP => VirtualKeyCode::P,
+        Code::KeyQ => VirtualKeyCode::Q,
+        Code::KeyR => VirtualKeyCode::R,
+        Code::KeyS => VirtualKeyCode::S,
+        Code::KeyT => VirtualKeyCode::T,
+        Code::KeyU => VirtualKeyCode::U,
+        Code::KeyV => VirtualKeyCode::V,
+        Code::KeyW => VirtualKeyCode::W,
+        Code::KeyX => VirtualKeyCode::X,
+        Code::KeyY => VirtualKeyCode::Y,
+        Code::KeyZ => VirtualKeyCode::Z,
+
+        Code::Escape => VirtualKeyCode::Escape,
+        Code::F1 => VirtualKeyCode::F1,
+        Code::F2 => VirtualKeyCode::F2,
+        Code::F3 => VirtualKeyCode::F3,
+        Code::F4 => VirtualKeyCode::F4,
+        Code::F5 => VirtualKeyCode::F5,
+        Code::F6 => VirtualKeyCode::F6,
+        Code::F7 => VirtualKeyCode::F7,
+        Code::F8 => VirtualKeyCode::F8,
+        Code::F9 => VirtualKeyCode::F9,
+        Code::F10 => VirtualKeyCode::F10,
+        Code::F11 => VirtualKeyCode::F11,
+        Code::F12 => VirtualKeyCode::F12,
+        Code::F13 => VirtualKeyCode::F13,
+        Code::F14 => VirtualKeyCode::F14,
+        Code::F15 => VirtualKeyCode::F15,
+        Code::F16 => VirtualKeyCode::F16,
+        Code::F17 => VirtualKeyCode::F17,
+        Code::F18 => VirtualKeyCode::F18,
+        Code::F19 => VirtualKeyCode::F19,
+        Code::F20 => VirtualKeyCode::F20,
+        Code::F21 => VirtualKeyCode::F21,
+        Code::F22 => VirtualKeyCode::F22,
+        Code::F23 => VirtualKeyCode::F23,
+        Code::F24 => VirtualKeyCode::F24,
+
+        Code::PrintScreen => VirtualKeyCode::Snapshot,
+        Code::ScrollLock => VirtualKeyCode::Scroll,
+        Code::Pause => VirtualKeyCode::Pause,
+
+        Code::Insert => VirtualKeyCode::Insert,
+        Code::Home => VirtualKeyCode::Home,
+        Code::Delete => VirtualKeyCode::Delete,
+        Code::End => VirtualKeyCode::End,
+        Code::PageDown => VirtualKeyCode::PageDown,
+        Code::PageUp => VirtualKeyCode::PageUp,
+
+        Code::ArrowLeft => VirtualKeyCode::Left,
+        Code::ArrowUp => VirtualKeyCode::Up,
+        Code::ArrowRight => VirtualKeyCode::Right,
+        Code::ArrowDown => VirtualKeyCode::Down,
+
+        Code::Backspace => VirtualKeyCode::Back,
+        Code::Enter => VirtualKeyCode::Return,
+        Code::Space => VirtualKeyCode::Space,
+        Code::Tab => VirtualKeyCode::Tab,
+        Code::CapsLock => VirtualKeyCode::Capital,
+        Code::ContextMenu => VirtualKeyCode::Apps,
+
+        Code::NumLock => VirtualKeyCode::Numlock,
+        Code::Numpad0 => VirtualKeyCode::Numpad0,
+        Code::Numpad1 => VirtualKeyCode::Numpad1,
+        Code::Numpad2 => VirtualKeyCode::Numpad2,
+        Code::Numpad3 => VirtualKeyCode::Numpad3,
+        Code::Numpad4 => VirtualKeyCode::Numpad4,
+        Code::Numpad5 => VirtualKeyCode::Numpad5,
+        Code::Numpad6 => VirtualKeyCode::Numpad6,
+        Code::Numpad7 => VirtualKeyCode::Numpad7,
+        Code::Numpad8 => VirtualKeyCode::Numpad8,
+        Code::Numpad9 => VirtualKeyCode::Numpad9,
+        Code::NumpadAdd => VirtualKeyCode::NumpadAdd,
+        Code::NumpadDivide => VirtualKeyCode::NumpadDivide,
+        Code::NumpadDecimal => VirtualKeyCode::NumpadDecimal,
+        Code::NumpadComma => VirtualKeyCode::NumpadComma,
+        Code::NumpadEnter => VirtualKeyCode::NumpadEnter,
+        Code::NumpadEqual => VirtualKeyCode::NumpadEquals,
+        Code::NumpadMultiply => VirtualKeyCode::NumpadMultiply,
+        Code::NumpadSubtract => VirtualKeyCode::NumpadSubtract,
+
+        Code::Quote => VirtualKeyCode::Apostrophe,
+        Code::Backslash => VirtualKeyCode::Backslash,
+        Code::Comma => VirtualKeyCode::Comma,
+        Code::Equal => VirtualKeyCode::Equals,
+        Code::Backquote => VirtualKeyCode::Grave,
+        Code::BracketLeft => VirtualKeyCode::LBracket,
+        Code::BracketRight => VirtualKeyCode::RBracket,
+        Code::Minus => VirtualKeyCode::Minus,
+        Code::Period => VirtualKeyCode::Period,
+        Code::Semicolon => VirtualKeyCode::Semicolon,
+        Code::Slash => VirtualKeyCode::Slash,
+        Code::IntlBackslash => VirtualKeyCode::OEM102,
+        Code::IntlYen => VirtualKeyCode::Yen,
+
+        Code::AltLeft => VirtualKeyCode::LAlt,
+        Code::AltRight => VirtualKeyCode::RAlt,
+        Code::ControlLeft => VirtualKeyCode::LControl,
+        Code::ControlRight => VirtualKeyCode::RControl,
+        Code::ShiftLeft => VirtualKeyCode::LShift,
+        Code::ShiftRight => VirtualKeyCode::RShift,
+        Code::MetaLeft => VirtualKeyCode::LWin,
+        Code::MetaRight => VirtualKeyCode::RWin,
+
+        Code::Convert => VirtualKeyCode::Convert,
+        Code::NonConvert => VirtualKeyCode::NoConvert,
+        Code::KanaMode => VirtualKeyCode::Kana,
+
+        Code::Copy => VirtualKeyCode::Copy,
+        Code::Cut => VirtualKeyCode::Cut,
+        Code::Paste => VirtualKeyCode::Paste,
+
+        Code::AudioVolumeDown => VirtualKeyCode::VolumeDown,
+        Code::AudioVolumeUp => VirtualKeyCode::VolumeUp,
+        Code::AudioVolumeMute => VirtualKeyCode::Mute,
+        Code::MediaPlayPause => VirtualKeyCode::PlayPause,
+        Code::MediaStop => VirtualKeyCode::MediaStop,
+        Code::MediaTrackNext => VirtualKeyCode::NextTrack,
+        Code::MediaTrackPrevious => VirtualKeyCode::PrevTrack,
+        Code::MediaSelect => VirtualKeyCode::MediaSelect,
+
+        _ => return None,
+    };
+
+    Some(keycode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use keyboard_types::{KeyState, Location, Modifiers};
+
+    fn key_down(key: Key, code: Code) -> KeyboardEvent {
+        KeyboardEvent {
+            state: KeyState::Down,
+            key,
+            code,
+            location: Location::Standard,
+            modifiers: Modifiers::empty(),
+            repeat: false,
+            is_composing: false,
+        }
+    }
+
+    #[test]
+    fn letters_follow_the_layout() {
+        // The key labelled A on an AZERTY keyboard is where Q is on a QWERTY keyboard
+        let e = key_down(Key::Character("a".to_owned()), Code::KeyQ);
+        assert_eq!(virtual_keycode(&e), Some(VirtualKeyCode::A));
+
+        let e = key_down(Key::Character("Z".to_owned()), Code::KeyW);
+        assert_eq!(virtual_keycode(&e), Some(VirtualKeyCode::Z));
+    }
+
+    #[test]
+    fn other_characters_use_the_key_position() {
+        // Shift+1 types '!' on a US layout
+        let e = key_down(Key::Character("!".to_owned()), Code::Digit1);
+        assert_eq!(virtual_keycode(&e), Some(VirtualKeyCode::Key1));
+
+        let e = key_down(Key::Character("ß".to_owned()), Code::Minus);
+        assert_eq!(virtual_keycode(&e), Some(VirtualKeyCode::Minus));
+    }
+
+    #[test]
+    fn numpad_digits_keep_their_own_keycodes() {
+        let e = key_down(Key::Character("7".to_owned()), Code::Numpad7);
+        assert_eq!(virtual_keycode(&e), Some(VirtualKeyCode::Numpad7));
+    }
+
+    #[test]
+    fn named_keys_use_the_key_position() {
+        let e = key_down(Key::Enter, Code::Enter);
+        assert_eq!(virtual_keycode(&e), Some(VirtualKeyCode::Return));
+
+        let e = key_down(Key::Enter, Code::NumpadEnter);
+        assert_eq!(virtual_keycode(&e), Some(VirtualKeyCode::NumpadEnter));
+
+        let e = key_down(Key::Control, Code::ControlRight);
+        assert_eq!(virtual_keycode(&e), Some(VirtualKeyCode::RControl));
+    }
+
+    #[test]
+    fn unknown_keys_have_no_keycode() {
+        let e = key_down(Key::Unidentified, Code::Unidentified);
+        assert_eq!(virtual_keycode(&e), None);
+    }
+}
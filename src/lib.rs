@@ -1,4 +1,24 @@
 mod application;
+mod clipboard;
+mod cursor;
+mod damage;
 mod event_manager;
+mod fonts;
+#[cfg(feature = "headless")]
+mod headless;
+mod hit_test;
+mod input;
+mod keyboard;
+mod render;
+#[cfg(feature = "testing")]
+mod snapshot;
+pub mod testing;
 
-pub use application::Application;
\ No newline at end of file
+pub use application::{Application, ApplicationBuilder, ApplicationHandle, WindowHandle};
+pub use clipboard::{ClipboardEvent, ClipboardProvider, MemoryClipboard, Selection};
+#[cfg(target_os = "linux")]
+pub use clipboard::{ClipboardError, X11Clipboard};
+pub use fonts::{FontData, FontError, FontEvent, FontSource};
+pub use input::{ClickEvent, CursorEvent, InputSettings, KeyEvent};
+#[cfg(feature = "headless")]
+pub use headless::{HeadlessError, HeadlessWindow};
\ No newline at end of file
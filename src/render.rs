@@ -0,0 +1,135 @@
+use femtovg::{renderer::OpenGl, Canvas, Color, ImageFlags, ImageId, Paint, Path, PixelFormat, RenderTarget};
+
+use tuix::State;
+
+use crate::damage::{DamageRegion, Rect};
+use crate::event_manager::EventManager;
+
+// Images used to draw only the damaged parts of the window
+#[derive(Clone, Copy)]
+struct RenderTargets {
+    scratch: ImageId,
+    contents: ImageId,
+    width: u32,
+    height: u32,
+}
+
+/// Draws the damaged parts of a window into render targets which keep the window contents between
+/// frames, and then shows the contents on the screen. Used by baseview and headless windows alike.
+pub(crate) struct DamageRenderer {
+    targets: Option<RenderTargets>,
+    // Outline the areas which were drawn again each frame
+    show_damage: bool,
+}
+
+impl DamageRenderer {
+    pub fn new(show_damage: bool) -> Self {
+        DamageRenderer {
+            targets: None,
+            show_damage,
+        }
+    }
+
+    /// Draw the damaged parts of the window, with the canvas sized to the window in physical pixels
+    pub fn render(
+        &mut self,
+        canvas: &mut Canvas<OpenGl>,
+        state: &mut State,
+        event_manager: &mut EventManager,
+        mut damage: DamageRegion,
+        scale_factor: f32,
+    ) {
+        // Layout is in logical pixels but the canvas is sized in physical pixels
+        let logical_width = state.transform.get_width(state.root);
+        let logical_height = state.transform.get_height(state.root);
+        let width = (logical_width * scale_factor).round() as u32;
+        let height = (logical_height * scale_factor).round() as u32;
+
+        let targets = match self.targets {
+            Some(targets) if targets.width == width && targets.height == height => targets,
+            _ => {
+                // New targets have no content yet
+                damage.add_full();
+                self.create_targets(canvas, width, height)
+            }
+        };
+
+        let rects = damage.rects(Rect::new(0.0, 0.0, logical_width, logical_height));
+
+        // Draw everything which intersects the damage into the scratch target. Widgets set their own
+        // scissor, so this may also draw outside of the damaged areas.
+        canvas.set_render_target(RenderTarget::Image(targets.scratch));
+
+        for rect in rects.iter() {
+            let (x, y, w, h) = rect.to_physical(scale_factor);
+            canvas.clear_rect(x, y, w, h, Color::rgb(80, 80, 80));
+        }
+
+        let hierarchy = state.hierarchy.clone();
+
+        canvas.save();
+        canvas.scale(scale_factor, scale_factor);
+
+        event_manager.draw(state, &hierarchy, canvas, &damage);
+
+        canvas.restore();
+
+        // Copy only the damaged areas into the target which holds the window contents between frames
+        canvas.set_render_target(RenderTarget::Image(targets.contents));
+
+        for rect in rects.iter() {
+            let (x, y, w, h) = rect.to_physical(scale_factor);
+            let mut path = Path::new();
+            path.rect(x as f32, y as f32, w as f32, h as f32);
+            canvas.fill_path(
+                &mut path,
+                Paint::image(targets.scratch, 0.0, 0.0, width as f32, height as f32, 0.0, 1.0),
+            );
+        }
+
+        // Show the window contents
+        canvas.set_render_target(RenderTarget::Screen);
+
+        let mut path = Path::new();
+        path.rect(0.0, 0.0, width as f32, height as f32);
+        canvas.fill_path(
+            &mut path,
+            Paint::image(targets.contents, 0.0, 0.0, width as f32, height as f32, 0.0, 1.0),
+        );
+
+        if self.show_damage {
+            for rect in rects.iter() {
+                let (x, y, w, h) = rect.to_physical(scale_factor);
+                let mut path = Path::new();
+                path.rect(x as f32 + 0.5, y as f32 + 0.5, w as f32 - 1.0, h as f32 - 1.0);
+                let mut paint = Paint::color(Color::rgba(255, 0, 0, 160));
+                paint.set_line_width(1.0);
+                canvas.stroke_path(&mut path, paint);
+            }
+        }
+    }
+
+    fn create_targets(&mut self, canvas: &mut Canvas<OpenGl>, width: u32, height: u32) -> RenderTargets {
+        if let Some(targets) = self.targets.take() {
+            canvas.delete_image(targets.scratch);
+            canvas.delete_image(targets.contents);
+        }
+
+        let mut create_image = || {
+            canvas
+                .create_image_empty(width as usize, height as usize, PixelFormat::Rgba8, ImageFlags::FLIP_Y)
+                .expect("Cannot create render target")
+        };
+
+        let targets = RenderTargets {
+            scratch: create_image(),
+            contents: create_image(),
+            width,
+            height,
+        };
+
+        self.targets = Some(targets);
+
+        targets
+    }
+}
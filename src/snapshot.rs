@@ -0,0 +1,376 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use image::{Rgba, RgbaImage};
+
+use tuix::window::WindowDescription;
+use tuix::{Entity, State};
+
+use crate::application::ApplicationBuilder;
+use crate::headless::HeadlessError;
+
+/// An error from rendering a snapshot or comparing it to a golden image
+#[derive(Debug)]
+pub enum SnapshotError {
+    Headless(HeadlessError),
+    Image(image::ImageError),
+    /// There is no golden image at `path`. The rendered image is written to `actual_path`.
+    MissingGolden { path: PathBuf, actual_path: PathBuf },
+    /// The rendered image has a different size to the golden image
+    SizeMismatch {
+        expected: (u32, u32),
+        actual: (u32, u32),
+        actual_path: PathBuf,
+    },
+    /// More pixels differ from the golden image than the tolerance allows
+    Mismatch {
+        differing_pixels: usize,
+        actual_path: PathBuf,
+        diff_path: PathBuf,
+    },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Headless(err) => write!(f, "{}", err),
+            SnapshotError::Image(err) => write!(f, "{}", err),
+            SnapshotError::MissingGolden { path, actual_path } => write!(
+                f,
+                "there is no golden image at {}, see {} and set TUIX_UPDATE_SNAPSHOTS=1 to use it",
+                path.display(),
+                actual_path.display()
+            ),
+            SnapshotError::SizeMismatch { expected, actual, actual_path } => write!(
+                f,
+                "snapshot is {}x{} but the golden image is {}x{}, see {}",
+                actual.0,
+                actual.1,
+                expected.0,
+                expected.1,
+                actual_path.display()
+            ),
+            SnapshotError::Mismatch { differing_pixels, actual_path, diff_path } => write!(
+                f,
+                "{} pixels differ from the golden image, see {} and {}",
+                differing_pixels,
+                actual_path.display(),
+                diff_path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<HeadlessError> for SnapshotError {
+    fn from(err: HeadlessError) -> Self {
+        SnapshotError::Headless(err)
+    }
+}
+
+impl From<image::ImageError> for SnapshotError {
+    fn from(err: image::ImageError) -> Self {
+        SnapshotError::Image(err)
+    }
+}
+
+/// Renders a tuix UI offscreen and compares it to a golden image
+pub struct Snapshot<F> {
+    builder: ApplicationBuilder<F>,
+    scale_factor: f64,
+    frames: usize,
+    tolerance: u8,
+    max_differing_pixels: usize,
+}
+
+impl<F: FnMut(WindowDescription, &mut State, Entity) -> WindowDescription> Snapshot<F> {
+    /// Takes the same closure as `Application::new`
+    pub fn new(app: F) -> Self {
+        Self::from_builder(ApplicationBuilder::new(app))
+    }
+
+    /// Use a builder for its fonts and themes. Its window size is used unless `inner_size` is called.
+    pub fn from_builder(builder: ApplicationBuilder<F>) -> Self {
+        Snapshot {
+            builder,
+            scale_factor: 1.0,
+            frames: 1,
+            tolerance: 0,
+            max_differing_pixels: 0,
+        }
+    }
+
+    /// Set the logical size of the window. The app closure can still override it.
+    pub fn inner_size(mut self, width: u32, height: u32) -> Self {
+        self.builder = self.builder.inner_size(width, height);
+        self
+    }
+
+    pub fn scale_factor(mut self, scale_factor: f64) -> Self {
+        self.scale_factor = scale_factor;
+        self
+    }
+
+    pub fn theme(mut self, theme: &str) -> Self {
+        self.builder = self.builder.theme(theme);
+        self
+    }
+
+    /// The number of frames to render before taking the snapshot, e.g. to let layout settle
+    pub fn frames(mut self, frames: usize) -> Self {
+        self.frames = frames.max(1);
+        self
+    }
+
+    /// The largest difference allowed in any colour channel before a pixel counts as different
+    pub fn tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// The number of pixels allowed to differ before the comparison fails
+    pub fn max_differing_pixels(mut self, max_differing_pixels: usize) -> Self {
+        self.max_differing_pixels = max_differing_pixels;
+        self
+    }
+
+    /// Render the UI and return the last frame
+    pub fn render(mut self) -> Result<RgbaImage, SnapshotError> {
+        let mut window = self.builder.open_headless(self.scale_factor)?;
+
+        for _ in 0..self.frames {
+            window.render();
+        }
+
+        Ok(window.screenshot()?)
+    }
+
+    /// Render the UI and compare it to the golden image at `path`.
+    ///
+    /// On failure the rendered image and an image highlighting the differing pixels are written
+    /// next to the golden image, with `.actual.png` and `.diff.png` extensions. The golden image
+    /// must exist unless `TUIX_UPDATE_SNAPSHOTS` is set, in which case it's written instead.
+    pub fn compare<P: AsRef<Path>>(self, path: P) -> Result<(), SnapshotError> {
+        let tolerance = self.tolerance;
+        let max_differing_pixels = self.max_differing_pixels;
+        let update = std::env::var_os("TUIX_UPDATE_SNAPSHOTS").is_some();
+
+        let actual = self.render()?;
+
+        compare_image(&actual, path.as_ref(), tolerance, max_differing_pixels, update)
+    }
+
+    /// Like `compare`, but panics with a description of the failure
+    pub fn assert_matches<P: AsRef<Path>>(self, path: P) {
+        if let Err(err) = self.compare(path) {
+            panic!("Snapshot doesn't match: {}", err);
+        }
+    }
+}
+
+// Compares a rendered image to the golden image at `path`, or replaces the golden image with it
+// when updating
+fn compare_image(
+    actual: &RgbaImage,
+    path: &Path,
+    tolerance: u8,
+    max_differing_pixels: usize,
+    update: bool,
+) -> Result<(), SnapshotError> {
+    let actual_path = path.with_extension("actual.png");
+
+    if update {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(image::ImageError::IoError)?;
+        }
+
+        actual.save(path)?;
+        return Ok(());
+    }
+
+    if !path.exists() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(image::ImageError::IoError)?;
+        }
+
+        actual.save(&actual_path)?;
+
+        return Err(SnapshotError::MissingGolden {
+            path: path.to_path_buf(),
+            actual_path,
+        });
+    }
+
+    let expected = image::open(path)?.into_rgba8();
+
+    if expected.dimensions() != actual.dimensions() {
+        actual.save(&actual_path)?;
+
+        return Err(SnapshotError::SizeMismatch {
+            expected: expected.dimensions(),
+            actual: actual.dimensions(),
+            actual_path,
+        });
+    }
+
+    let (diff, differing_pixels) = diff_images(&expected, actual, tolerance);
+
+    if differing_pixels > max_differing_pixels {
+        let diff_path = path.with_extension("diff.png");
+
+        actual.save(&actual_path)?;
+        diff.save(&diff_path)?;
+
+        return Err(SnapshotError::Mismatch {
+            differing_pixels,
+            actual_path,
+            diff_path,
+        });
+    }
+
+    Ok(())
+}
+
+// Returns an image with differing pixels in red over a faded copy of the expected image,
+// along with the number of differing pixels
+fn diff_images(expected: &RgbaImage, actual: &RgbaImage, tolerance: u8) -> (RgbaImage, usize) {
+    let mut differing_pixels = 0;
+
+    let diff = RgbaImage::from_fn(expected.width(), expected.height(), |x, y| {
+        let expected_pixel = expected.get_pixel(x, y);
+        let actual_pixel = actual.get_pixel(x, y);
+
+        let differs = expected_pixel
+            .0
+            .iter()
+            .zip(actual_pixel.0.iter())
+            .any(|(a, b)| (*a as i16 - *b as i16).abs() > tolerance as i16);
+
+        if differs {
+            differing_pixels += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            let [r, g, b, _] = expected_pixel.0;
+            Rgba([r / 4, g / 4, b / 4, 255])
+        }
+    });
+
+    (diff, differing_pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A directory for the golden images of one test, emptied first
+    fn snapshot_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("tuix_baseview_snapshots").join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    // Moves a channel towards the middle so it can't wrap around
+    fn shift(channel: u8, by: u8) -> u8 {
+        if channel < 128 {
+            channel + by
+        } else {
+            channel - by
+        }
+    }
+
+    // A 20x10 gradient, so the comparisons don't depend on rendering
+    fn test_image() -> RgbaImage {
+        RgbaImage::from_fn(20, 10, |x, y| Rgba([x as u8 * 12, y as u8 * 25, 128, 255]))
+    }
+
+    #[test]
+    fn renders_at_the_physical_size() {
+        let image = Snapshot::new(|win_desc, _state, _window| win_desc)
+            .inner_size(20, 10)
+            .render()
+            .unwrap();
+
+        assert_eq!(image.dimensions(), (20, 10));
+
+        let image = Snapshot::new(|win_desc, _state, _window| win_desc)
+            .inner_size(20, 10)
+            .scale_factor(2.0)
+            .render()
+            .unwrap();
+
+        assert_eq!(image.dimensions(), (40, 20));
+    }
+
+    #[test]
+    fn counts_pixels_beyond_the_tolerance() {
+        let expected = test_image();
+        let mut actual = expected.clone();
+
+        let (_, differing_pixels) = diff_images(&expected, &actual, 0);
+        assert_eq!(differing_pixels, 0);
+
+        let Rgba([r, g, b, a]) = *actual.get_pixel(3, 4);
+        actual.put_pixel(3, 4, Rgba([shift(r, 5), g, b, a]));
+        let Rgba([r, g, b, a]) = *actual.get_pixel(7, 8);
+        actual.put_pixel(7, 8, Rgba([r, g, shift(b, 20), a]));
+
+        let (diff, differing_pixels) = diff_images(&expected, &actual, 0);
+        assert_eq!(differing_pixels, 2);
+        assert_eq!(*diff.get_pixel(3, 4), Rgba([255, 0, 0, 255]));
+        assert_eq!(*diff.get_pixel(7, 8), Rgba([255, 0, 0, 255]));
+        assert_ne!(*diff.get_pixel(0, 0), Rgba([255, 0, 0, 255]));
+
+        assert_eq!(diff_images(&expected, &actual, 5).1, 1);
+        assert_eq!(diff_images(&expected, &actual, 20).1, 0);
+    }
+
+    #[test]
+    fn missing_golden_images_fail_unless_updating() {
+        let dir = snapshot_dir("missing_golden");
+        let path = dir.join("trivial.png");
+        let actual = test_image();
+
+        match compare_image(&actual, &path, 0, 0, false) {
+            Err(SnapshotError::MissingGolden { actual_path, .. }) => assert!(actual_path.exists()),
+            result => panic!("Expected a missing golden image, got {:?}", result),
+        }
+        assert!(!path.exists());
+
+        compare_image(&actual, &path, 0, 0, true).unwrap();
+        assert!(path.exists());
+
+        compare_image(&actual, &path, 0, 0, false).unwrap();
+    }
+
+    #[test]
+    fn mismatches_are_allowed_up_to_the_limit() {
+        let dir = snapshot_dir("mismatch");
+        let path = dir.join("trivial.png");
+        let expected = test_image();
+
+        compare_image(&expected, &path, 0, 0, true).unwrap();
+
+        let mut actual = expected.clone();
+        let Rgba([r, g, b, a]) = *actual.get_pixel(0, 0);
+        actual.put_pixel(0, 0, Rgba([shift(r, 10), g, b, a]));
+
+        match compare_image(&actual, &path, 0, 0, false) {
+            Err(SnapshotError::Mismatch { differing_pixels, actual_path, diff_path }) => {
+                assert_eq!(differing_pixels, 1);
+                assert!(actual_path.exists());
+                assert!(diff_path.exists());
+            }
+            result => panic!("Expected a mismatch, got {:?}", result),
+        }
+
+        compare_image(&actual, &path, 10, 0, false).unwrap();
+        compare_image(&actual, &path, 0, 1, false).unwrap();
+
+        let smaller = RgbaImage::new(10, 10);
+        assert!(matches!(
+            compare_image(&smaller, &path, 0, 0, false),
+            Err(SnapshotError::SizeMismatch { expected: (20, 10), actual: (10, 10), .. })
+        ));
+    }
+}
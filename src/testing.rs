@@ -0,0 +1,239 @@
+//! Golden image and interaction tests for tuix UIs.
+//!
+//! Input can be simulated with a `TestWindow`, which translates it the same way as a baseview
+//! window so hover, active, capture and focus can be checked afterwards. It needs neither a display
+//! nor a renderer, so it's always available.
+//!
+//! ```no_run
+//! use tuix_baseview::testing::TestWindow;
+//!
+//! let mut window = TestWindow::new(|win_desc, state, window| {
+//!     // Build the widget tree
+//!     win_desc
+//! });
+//!
+//! window.drag((150.0, 150.0), (150.0, 100.0), 10);
+//! ```
+//!
+//! With the `testing` feature, a `Snapshot` renders a UI offscreen with the headless backend, so no
+//! GPU or display is needed, and compares it to a golden image.
+//!
+//! ```ignore
+//! use tuix_baseview::testing::Snapshot;
+//!
+//! Snapshot::new(|win_desc, state, window| {
+//!     // Build the widget tree
+//!     win_desc
+//! })
+//! .inner_size(300, 300)
+//! .theme(include_str!("../examples/theme.css"))
+//! .assert_matches("tests/snapshots/gain_widget.png");
+//! ```
+//!
+//! `tests/snapshot.rs` compares the gain widget from the examples this way. A missing golden image
+//! fails the comparison, so a snapshot can't pass without one being checked in. Set `TUIX_UPDATE_SNAPSHOTS=1` to write new golden images, or to overwrite existing ones after
+//! an intended change.
+
+use tuix::window::WindowDescription;
+use tuix::{CursorIcon, Entity, State};
+
+use keyboard_types::{Code, Key, KeyState, KeyboardEvent, Location, Modifiers};
+
+use crate::application::ApplicationBuilder;
+use crate::clipboard::ClipboardProvider;
+use crate::event_manager::EventManager;
+use crate::input::InputHandler;
+
+#[cfg(feature = "testing")]
+pub use crate::snapshot::{Snapshot, SnapshotError};
+
+/// A tuix window without a platform window or renderer, driven by synthetic input.
+///
+/// Input goes through the same translation as events from a baseview window, and the resulting
+/// events are handled before each method returns.
+pub struct TestWindow {
+    state: State,
+    event_manager: EventManager,
+    input: InputHandler,
+    modifiers: Modifiers,
+    cursor: CursorIcon,
+}
+
+impl TestWindow {
+    // The most frames run after each input, for events sent by event handlers and for animations
+    const MAX_FRAMES: usize = 16;
+
+    /// Takes the same closure as `Application::new`
+    pub fn new<F: FnMut(WindowDescription, &mut State, Entity) -> WindowDescription>(app: F) -> Self {
+        Self::from_builder(ApplicationBuilder::new(app))
+    }
+
+    /// Use a builder for its window size, themes and input settings
+    pub fn from_builder<F: FnMut(WindowDescription, &mut State, Entity) -> WindowDescription>(
+        mut builder: ApplicationBuilder<F>,
+    ) -> Self {
+        let (state, _) = builder.build_state();
+        let input = builder.input_handler();
+
+        let mut window = TestWindow {
+            state,
+            event_manager: EventManager::new(),
+            input,
+            modifiers: Modifiers::empty(),
+            cursor: CursorIcon::Arrow,
+        };
+
+        window.flush();
+        window
+    }
+
+    pub fn state(&mut self) -> &mut State {
+        &mut self.state
+    }
+
+    /// The cursor the window would show
+    pub fn cursor(&self) -> CursorIcon {
+        self.cursor
+    }
+
+    /// The text on the clipboard, which is kept in memory rather than using the system clipboard
+    pub fn clipboard_text(&mut self) -> Option<String> {
+        self.event_manager.clipboard.get_text()
+    }
+
+    pub fn set_clipboard_text(&mut self, text: &str) {
+        self.event_manager.clipboard.set_text(text.to_owned());
+    }
+
+    /// Use another clipboard instead of the one in memory, e.g. one which is slow to answer
+    pub fn set_clipboard(&mut self, clipboard: Box<dyn ClipboardProvider>) {
+        self.event_manager.clipboard = clipboard;
+    }
+
+    pub fn primary_selection(&mut self) -> Option<String> {
+        self.event_manager.clipboard.get_primary_text()
+    }
+
+    pub fn set_primary_selection(&mut self, text: &str) {
+        self.event_manager.clipboard.set_primary_text(text.to_owned());
+    }
+
+    /// The modifiers held down for the following mouse and keyboard events
+    pub fn set_modifiers(&mut self, modifiers: Modifiers) {
+        self.modifiers = modifiers;
+    }
+
+    /// Translate a baseview event the same way a baseview window does, returning whether any event
+    /// handler consumed it, and then run frames until the events sent by the handlers are handled
+    pub fn send(&mut self, event: baseview::Event) -> baseview::EventStatus {
+        let status = self.input.handle_input(&mut self.event_manager, &mut self.state, event);
+        self.flush();
+
+        status
+    }
+
+    /// Run frames the way a window does, without drawing them, until no events are left
+    pub fn flush(&mut self) {
+        for _ in 0..Self::MAX_FRAMES {
+            self.event_manager.update(&mut self.state);
+
+            if let Some(cursor) = self.event_manager.cursor.take() {
+                self.cursor = cursor;
+            }
+
+            if self.state.event_queue.is_empty() {
+                break;
+            }
+        }
+
+        // Nothing is drawn, so damage would only accumulate
+        self.event_manager.damage.clear();
+    }
+
+    /// Move the cursor to a position in logical pixels
+    pub fn mouse_move(&mut self, x: f32, y: f32) {
+        self.send(baseview::Event::Mouse(baseview::MouseEvent::CursorMoved {
+            position: baseview::Point::new(x as f64, y as f64),
+            modifiers: self.modifiers,
+        }));
+    }
+
+    pub fn mouse_enter(&mut self) {
+        self.send(baseview::Event::Mouse(baseview::MouseEvent::CursorEntered));
+    }
+
+    pub fn mouse_leave(&mut self) {
+        self.send(baseview::Event::Mouse(baseview::MouseEvent::CursorLeft));
+    }
+
+    pub fn mouse_down(&mut self, button: baseview::MouseButton) {
+        self.send(baseview::Event::Mouse(baseview::MouseEvent::ButtonPressed {
+            button,
+            modifiers: self.modifiers,
+        }));
+    }
+
+    pub fn mouse_up(&mut self, button: baseview::MouseButton) {
+        self.send(baseview::Event::Mouse(baseview::MouseEvent::ButtonReleased {
+            button,
+            modifiers: self.modifiers,
+        }));
+    }
+
+    /// Move the cursor to a position and click the left button there
+    pub fn click(&mut self, x: f32, y: f32) {
+        self.mouse_move(x, y);
+        self.mouse_down(baseview::MouseButton::Left);
+        self.mouse_up(baseview::MouseButton::Left);
+    }
+
+    /// Press the left button at `from`, move to `to` in `steps` equal moves and release it there
+    pub fn drag(&mut self, from: (f32, f32), to: (f32, f32), steps: usize) {
+        let steps = steps.max(1);
+
+        self.mouse_move(from.0, from.1);
+        self.mouse_down(baseview::MouseButton::Left);
+
+        for step in 1..=steps {
+            let t = step as f32 / steps as f32;
+            self.mouse_move(from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t);
+        }
+
+        self.mouse_up(baseview::MouseButton::Left);
+    }
+
+    /// Scroll by a number of lines at the current cursor position
+    pub fn scroll(&mut self, x: f32, y: f32) {
+        self.send(baseview::Event::Mouse(baseview::MouseEvent::WheelScrolled {
+            delta: baseview::ScrollDelta::Lines { x, y },
+            modifiers: self.modifiers,
+        }));
+    }
+
+    /// Press and release a key, returning whether the key press was consumed
+    pub fn key_press(&mut self, key: Key, code: Code) -> baseview::EventStatus {
+        let status = self.key_event(KeyState::Down, key.clone(), code);
+        self.key_event(KeyState::Up, key, code);
+
+        status
+    }
+
+    /// Press and release a key for each character of the text
+    pub fn type_text(&mut self, text: &str) {
+        for chr in text.chars() {
+            self.key_press(Key::Character(chr.to_string()), Code::Unidentified);
+        }
+    }
+
+    fn key_event(&mut self, state: KeyState, key: Key, code: Code) -> baseview::EventStatus {
+        self.send(baseview::Event::Keyboard(KeyboardEvent {
+            state,
+            key,
+            code,
+            location: Location::Standard,
+            modifiers: self.modifiers,
+            repeat: false,
+            is_composing: false,
+        }))
+    }
+}
@@ -0,0 +1,151 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use tuix::widgets::slider::SliderEvent;
+use tuix::widgets::value_knob::*;
+use tuix::window::WindowEvent;
+use tuix::{BuildHandler, CursorIcon, Entity, Event, EventHandler, State};
+
+use tuix_baseview::testing::TestWindow;
+use tuix_baseview::ApplicationBuilder;
+
+static THEME: &str = include_str!("../examples/theme.css");
+
+#[derive(Default)]
+struct Recorded {
+    values: Vec<f32>,
+    // The typed characters along with the entity they were sent to
+    chars: Vec<(char, Entity)>,
+}
+
+// The gain widget from the example, recording the values of its knob and the text typed into it
+struct GainWidget {
+    recorded: Rc<RefCell<Recorded>>,
+}
+
+impl BuildHandler for GainWidget {
+    type Ret = Entity;
+    fn on_build(&mut self, state: &mut State, entity: Entity) -> Self::Ret {
+        ValueKnob::new("Gain", 1.0, 0.0, 1.0).build(state, entity, |builder| builder);
+
+        entity
+    }
+}
+
+impl EventHandler for GainWidget {
+    fn on_event(&mut self, _state: &mut State, _entity: Entity, event: &mut Event) -> bool {
+        if let Some(SliderEvent::ValueChanged(value)) = event.message.downcast::<SliderEvent>() {
+            self.recorded.borrow_mut().values.push(*value);
+        }
+
+        // Text input passes through the ancestors of the focused entity
+        if let Some(WindowEvent::CharInput(chr)) = event.message.downcast::<WindowEvent>() {
+            self.recorded.borrow_mut().chars.push((*chr, event.target));
+        }
+
+        false
+    }
+}
+
+fn gain_window() -> (TestWindow, Rc<RefCell<Recorded>>) {
+    let recorded = Rc::new(RefCell::new(Recorded::default()));
+
+    let window = {
+        let recorded = recorded.clone();
+
+        TestWindow::from_builder(
+            ApplicationBuilder::new(move |win_desc, state, window| {
+                GainWidget { recorded: recorded.clone() }.build(state, window, |builder| builder);
+
+                win_desc.with_inner_size(300, 300)
+            })
+            .theme(THEME),
+        )
+    };
+
+    (window, recorded)
+}
+
+// The center of the first entity with an element name, e.g. `knob`
+fn center_of(window: &mut TestWindow, element: &str) -> (f32, f32) {
+    let state = window.state();
+
+    let entity = state
+        .hierarchy
+        .into_iter()
+        .find(|entity| state.style.elements.get(*entity).map(|name| name.as_str()) == Some(element))
+        .unwrap_or_else(|| panic!("No {} in the gain widget", element));
+
+    (
+        state.transform.get_posx(entity) + state.transform.get_width(entity) / 2.0,
+        state.transform.get_posy(entity) + state.transform.get_height(entity) / 2.0,
+    )
+}
+
+#[test]
+fn dragging_the_knob_down_lowers_the_gain() {
+    let (mut window, recorded) = gain_window();
+
+    let (x, y) = center_of(&mut window, "knob");
+    window.drag((x, y), (x, y + 50.0), 10);
+
+    let recorded = recorded.borrow();
+    let value = *recorded.values.last().expect("Dragging the knob didn't change its value");
+
+    assert!(value < 1.0, "the gain is still {} after dragging the knob down", value);
+    assert!(value >= 0.0);
+}
+
+#[test]
+fn hovering_the_knob_shows_the_theme_cursor() {
+    let (mut window, _) = gain_window();
+
+    let root = window.state().root;
+
+    let (x, y) = center_of(&mut window, "knob");
+    window.mouse_move(x, y);
+
+    let knob = window.state().hovered;
+    assert_ne!(knob, root);
+    assert_eq!(window.cursor(), CursorIcon::NsResize);
+
+    // The bottom right corner is outside of the knob
+    window.mouse_move(299.0, 299.0);
+
+    assert_ne!(window.state().hovered, knob);
+    assert_eq!(window.cursor(), CursorIcon::Arrow);
+}
+
+#[test]
+fn themes_inserted_by_the_app_set_cursors_too() {
+    let recorded = Rc::new(RefCell::new(Recorded::default()));
+
+    let mut window = TestWindow::new(move |win_desc, state, window| {
+        state.insert_theme(THEME);
+        GainWidget { recorded: recorded.clone() }.build(state, window, |builder| builder);
+
+        win_desc.with_inner_size(300, 300)
+    });
+
+    let (x, y) = center_of(&mut window, "knob");
+    window.mouse_move(x, y);
+
+    assert_eq!(window.cursor(), CursorIcon::NsResize);
+}
+
+#[test]
+fn clicking_the_textbox_focuses_it_for_typing() {
+    let (mut window, recorded) = gain_window();
+    let root = window.state().root;
+
+    let (x, y) = center_of(&mut window, "textbox");
+    window.click(x, y);
+
+    let textbox = window.state().hovered;
+    assert_ne!(textbox, root);
+    assert_eq!(window.state().focused, textbox);
+
+    window.type_text("0.5");
+
+    assert_eq!(recorded.borrow().chars, vec![('0', textbox), ('.', textbox), ('5', textbox)]);
+}
@@ -0,0 +1,345 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use tuix::state::mouse::{MouseButton, MouseButtonData, MouseButtonState};
+use tuix::window::WindowEvent;
+use tuix::{BuildHandler, Entity, Event, EventHandler, Length, State};
+
+use keyboard_types::{Code, Key, KeyState, KeyboardEvent, Location, Modifiers};
+
+use tuix_baseview::testing::TestWindow;
+use tuix_baseview::{ClipboardEvent, ClipboardProvider, KeyEvent, Selection};
+
+// Records the buttons of the mouse downs it receives
+struct ButtonRecorder {
+    buttons: Rc<RefCell<Vec<MouseButton>>>,
+}
+
+impl BuildHandler for ButtonRecorder {
+    type Ret = Entity;
+    fn on_build(&mut self, _state: &mut State, entity: Entity) -> Self::Ret {
+        entity
+    }
+}
+
+impl EventHandler for ButtonRecorder {
+    fn on_event(&mut self, _state: &mut State, _entity: Entity, event: &mut Event) -> bool {
+        if let Some(WindowEvent::MouseDown(button)) = event.message.downcast::<WindowEvent>() {
+            self.buttons.borrow_mut().push(*button);
+        }
+
+        false
+    }
+}
+
+// Records the keyboard events it receives
+struct KeyRecorder {
+    keys: Rc<RefCell<Vec<KeyEvent>>>,
+}
+
+impl BuildHandler for KeyRecorder {
+    type Ret = Entity;
+    fn on_build(&mut self, _state: &mut State, entity: Entity) -> Self::Ret {
+        entity
+    }
+}
+
+impl EventHandler for KeyRecorder {
+    fn on_event(&mut self, _state: &mut State, _entity: Entity, event: &mut Event) -> bool {
+        if let Some(key_event) = event.message.downcast::<KeyEvent>() {
+            self.keys.borrow_mut().push(key_event.clone());
+        }
+
+        false
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Ping;
+
+// Consumes `Ping`s, and key downs if `consume_keys` is set
+struct Consumer {
+    consume_keys: bool,
+}
+
+impl BuildHandler for Consumer {
+    type Ret = Entity;
+    fn on_build(&mut self, _state: &mut State, entity: Entity) -> Self::Ret {
+        entity
+    }
+}
+
+impl EventHandler for Consumer {
+    fn on_event(&mut self, _state: &mut State, _entity: Entity, event: &mut Event) -> bool {
+        if event.message.downcast::<Ping>().is_some() {
+            return true;
+        }
+
+        self.consume_keys && matches!(event.message.downcast::<WindowEvent>(), Some(WindowEvent::KeyDown(_)))
+    }
+}
+
+// A 200x200 window with a 100x100 recorder in the top left corner
+fn recorder_window() -> (TestWindow, Entity, Rc<RefCell<Vec<MouseButton>>>) {
+    let buttons = Rc::new(RefCell::new(Vec::new()));
+    let recorder = Rc::new(RefCell::new(Entity::null()));
+
+    let window = {
+        let buttons = buttons.clone();
+        let recorder = recorder.clone();
+
+        TestWindow::new(move |win_desc, state, window| {
+            *recorder.borrow_mut() = ButtonRecorder { buttons: buttons.clone() }.build(state, window, |builder| {
+                builder.set_width(Length::Pixels(100.0)).set_height(Length::Pixels(100.0))
+            });
+
+            win_desc.with_inner_size(200, 200)
+        })
+    };
+
+    let recorder = *recorder.borrow();
+
+    (window, recorder, buttons)
+}
+
+// Press a button over the recorder and release it outside of it
+fn check_button_tracking(button: baseview::MouseButton, data: fn(&mut State) -> &mut MouseButtonData) {
+    let (mut window, recorder, _) = recorder_window();
+
+    let root = window.state().root;
+    let left_state = window.state().mouse.left.state;
+    let left_pressed = window.state().mouse.left.pressed;
+
+    window.mouse_move(20.0, 30.0);
+    window.mouse_down(button);
+
+    let pressed = data(window.state());
+    assert_eq!(pressed.state, MouseButtonState::Pressed);
+    assert_eq!(pressed.pos_down, (20.0, 30.0));
+    assert_eq!(pressed.pressed, recorder);
+
+    window.mouse_move(150.0, 160.0);
+    window.mouse_up(button);
+
+    let released = data(window.state());
+    assert_eq!(released.state, MouseButtonState::Released);
+    assert_eq!(released.pos_down, (20.0, 30.0));
+    assert_eq!(released.pos_up, (150.0, 160.0));
+    assert_eq!(released.released, root);
+
+    // The left button isn't affected by the other buttons
+    assert_eq!(window.state().mouse.left.state, left_state);
+    assert_eq!(window.state().mouse.left.pressed, left_pressed);
+}
+
+#[test]
+fn middle_button_is_tracked() {
+    check_button_tracking(baseview::MouseButton::Middle, |state| &mut state.mouse.middle);
+}
+
+#[test]
+fn right_button_is_tracked() {
+    check_button_tracking(baseview::MouseButton::Right, |state| &mut state.mouse.right);
+}
+
+#[test]
+fn back_and_forward_use_the_x11_button_numbers() {
+    let (mut window, _, buttons) = recorder_window();
+
+    window.mouse_move(50.0, 50.0);
+
+    window.mouse_down(baseview::MouseButton::Back);
+    window.mouse_up(baseview::MouseButton::Back);
+    window.mouse_down(baseview::MouseButton::Forward);
+    window.mouse_up(baseview::MouseButton::Forward);
+
+    assert_eq!(*buttons.borrow(), vec![MouseButton::Other(8), MouseButton::Other(9)]);
+}
+
+#[test]
+fn buttons_outside_of_the_window_are_not_broadcast() {
+    let (mut window, _, buttons) = recorder_window();
+
+    window.mouse_move(50.0, 50.0);
+    window.mouse_leave();
+
+    // Nothing is hovered, so the press has no target
+    window.mouse_down(baseview::MouseButton::Left);
+    window.mouse_up(baseview::MouseButton::Left);
+
+    assert!(buttons.borrow().is_empty());
+}
+
+// A window with a focused key recorder
+fn key_recorder_window() -> (TestWindow, Rc<RefCell<Vec<KeyEvent>>>) {
+    let keys = Rc::new(RefCell::new(Vec::new()));
+    let recorder = Rc::new(RefCell::new(Entity::null()));
+
+    let mut window = {
+        let keys = keys.clone();
+        let recorder = recorder.clone();
+
+        TestWindow::new(move |win_desc, state, window| {
+            *recorder.borrow_mut() = KeyRecorder { keys: keys.clone() }.build(state, window, |builder| builder);
+
+            win_desc.with_inner_size(200, 200)
+        })
+    };
+
+    window.state().focused = *recorder.borrow();
+
+    (window, keys)
+}
+
+#[test]
+fn key_location_and_repeat_reach_the_focused_entity() {
+    let (mut window, keys) = key_recorder_window();
+
+    let enter = KeyboardEvent {
+        state: KeyState::Down,
+        key: Key::Enter,
+        code: Code::NumpadEnter,
+        location: Location::Numpad,
+        modifiers: Modifiers::empty(),
+        repeat: true,
+        is_composing: false,
+    };
+
+    window.send(baseview::Event::Keyboard(enter.clone()));
+
+    let released = KeyboardEvent { state: KeyState::Up, repeat: false, ..enter.clone() };
+    window.send(baseview::Event::Keyboard(released.clone()));
+
+    assert_eq!(*keys.borrow(), vec![KeyEvent::Pressed(enter), KeyEvent::Released(released)]);
+}
+
+#[test]
+fn ordinary_key_presses_only_send_key_down_and_up() {
+    let (mut window, keys) = key_recorder_window();
+
+    window.key_press(Key::Enter, Code::Enter);
+    window.type_text("a");
+
+    assert!(keys.borrow().is_empty());
+}
+
+fn consumer_window(consume_keys: bool) -> (TestWindow, Entity) {
+    let consumer = Rc::new(RefCell::new(Entity::null()));
+
+    let mut window = {
+        let consumer = consumer.clone();
+
+        TestWindow::new(move |win_desc, state, window| {
+            *consumer.borrow_mut() = Consumer { consume_keys }.build(state, window, |builder| builder);
+
+            win_desc.with_inner_size(200, 200)
+        })
+    };
+
+    let consumer = *consumer.borrow();
+    window.state().focused = consumer;
+
+    (window, consumer)
+}
+
+#[test]
+fn consumed_key_presses_are_captured() {
+    let (mut window, _) = consumer_window(true);
+
+    assert_eq!(window.key_press(Key::Enter, Code::Enter), baseview::EventStatus::Captured);
+}
+
+#[test]
+fn events_queued_before_the_input_are_not_counted_as_consuming_it() {
+    let (mut window, consumer) = consumer_window(false);
+
+    // Queued e.g. through an application handle, and handled along with the key press
+    window.state().insert_event(Event::new(Ping).target(consumer));
+
+    assert_eq!(window.key_press(Key::Enter, Code::Enter), baseview::EventStatus::Ignored);
+}
+
+// A clipboard whose requests are answered by the test
+struct SlowClipboard {
+    requests: Rc<RefCell<Vec<Sender<Option<String>>>>>,
+}
+
+impl ClipboardProvider for SlowClipboard {
+    fn get_text(&mut self) -> Option<String> {
+        None
+    }
+
+    fn set_text(&mut self, _text: String) {}
+
+    fn request_text(&mut self, _selection: Selection) -> Receiver<Option<String>> {
+        let (sender, receiver) = channel();
+        self.requests.borrow_mut().push(sender);
+
+        receiver
+    }
+}
+
+// Records the text pasted into it
+struct PasteRecorder {
+    pasted: Rc<RefCell<Vec<String>>>,
+}
+
+impl BuildHandler for PasteRecorder {
+    type Ret = Entity;
+    fn on_build(&mut self, _state: &mut State, entity: Entity) -> Self::Ret {
+        entity
+    }
+}
+
+impl EventHandler for PasteRecorder {
+    fn on_event(&mut self, _state: &mut State, _entity: Entity, event: &mut Event) -> bool {
+        if let Some(ClipboardEvent::Paste(text)) = event.message.downcast::<ClipboardEvent>() {
+            self.pasted.borrow_mut().push(text.clone());
+        }
+
+        false
+    }
+}
+
+#[test]
+fn clipboard_requests_are_answered_once_the_text_is_read() {
+    let pasted = Rc::new(RefCell::new(Vec::new()));
+    let recorder = Rc::new(RefCell::new(Entity::null()));
+
+    let mut window = {
+        let pasted = pasted.clone();
+        let recorder = recorder.clone();
+
+        TestWindow::new(move |win_desc, state, window| {
+            *recorder.borrow_mut() = PasteRecorder { pasted: pasted.clone() }.build(state, window, |builder| builder);
+
+            win_desc.with_inner_size(200, 200)
+        })
+    };
+
+    let recorder = *recorder.borrow();
+
+    let requests = Rc::new(RefCell::new(Vec::new()));
+    window.set_clipboard(Box::new(SlowClipboard { requests: requests.clone() }));
+
+    window.state().insert_event(Event::new(ClipboardEvent::Request).target(recorder).origin(recorder));
+    window.flush();
+
+    // Nothing is pasted while the clipboard is still being read
+    assert_eq!(requests.borrow().len(), 1);
+    assert!(pasted.borrow().is_empty());
+
+    requests.borrow()[0].send(Some("text".to_owned())).unwrap();
+    window.flush();
+
+    assert_eq!(*pasted.borrow(), vec!["text".to_owned()]);
+
+    // Requests without any text are dropped
+    window.state().insert_event(Event::new(ClipboardEvent::Request).target(recorder).origin(recorder));
+    window.flush();
+    requests.borrow()[1].send(None).unwrap();
+    window.flush();
+
+    assert_eq!(pasted.borrow().len(), 1);
+}
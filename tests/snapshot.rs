@@ -0,0 +1,25 @@
+use tuix::widgets::value_knob::*;
+use tuix::BuildHandler;
+
+use tuix_baseview::testing::Snapshot;
+
+static THEME: &str = include_str!("../examples/theme.css");
+
+// Golden images are written with `TUIX_UPDATE_SNAPSHOTS=1 cargo test --features testing`
+fn golden_image(name: &str) -> String {
+    format!("{}/tests/snapshots/{}.png", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+#[test]
+fn gain_widget_with_the_example_theme() {
+    Snapshot::new(|win_desc, state, window| {
+        ValueKnob::new("Gain", 1.0, 0.0, 1.0).build(state, window, |builder| builder);
+
+        win_desc
+    })
+    .inner_size(300, 300)
+    .theme(THEME)
+    // Font rasterization differs slightly between Mesa versions
+    .tolerance(8)
+    .assert_matches(golden_image("gain_widget"));
+}